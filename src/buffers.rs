@@ -0,0 +1,159 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}, sync::Arc};
+
+use anyhow::{bail, Result};
+use tokio::sync::RwLock;
+
+use crate::{primatives::{Text, View}, ARW};
+
+//
+// every open text, by id. windows only hold views of them, so a text stays
+// open after the last window showing it is closed, until it's deleted here
+//
+
+// ids start at 1. views with 0 show texts that aren't in the list, like message pages
+pub type BufferId = usize;
+
+#[derive(Default)]
+pub struct Buffers {
+    last: BufferId,
+    texts: BTreeMap<BufferId, ARW<Text>>,
+}
+
+impl Buffers {
+    // takes ownership of the text, handing back a view of it
+    pub fn add(&mut self, text: Text) -> View {
+        self.last += 1;
+        let text = Arc::new(RwLock::new(text));
+        self.texts.insert(self.last, text.clone());
+        View::new(self.last, text)
+    }
+
+    // the buffer already holding the file, or a new one read from it
+    pub async fn open(&mut self, path: &Path) -> Result<View> {
+        if let Some(id) = self.find_path(path).await {
+            return Ok(View::new(id, self.texts[&id].clone()))
+        }
+        Ok(self.add(Text::open(path)?))
+    }
+
+    async fn find_path(&self, path: &Path) -> Option<BufferId> {
+        let path = canonical(path);
+        for (id, text) in self.texts.iter() {
+            if text.read().await.path.as_deref().map(canonical).as_ref() == Some(&path) {
+                return Some(*id)
+            }
+        }
+        None
+    }
+
+    pub fn view(&self, id: BufferId) -> Option<View> {
+        self.texts.get(&id).map(|text| View::new(id, text.clone()))
+    }
+
+    pub fn texts(&self) -> Vec<ARW<Text>> {
+        self.texts.values().cloned().collect()
+    }
+
+    pub fn ids(&self) -> Vec<BufferId> {
+        self.texts.keys().copied().collect()
+    }
+
+    pub fn remove(&mut self, id: BufferId) -> Option<ARW<Text>> {
+        self.texts.remove(&id)
+    }
+
+    // a buffer number, or a piece of one buffer's name. a number that isn't
+    // a buffer's is looked for in the names, for files like 2024.log
+    pub async fn find(&self, arg: &str) -> Result<BufferId> {
+        let id = arg.parse::<BufferId>().ok();
+        if let Some(id) = id.filter(|id| self.texts.contains_key(id)) { return Ok(id) }
+        let mut found = Vec::new();
+        for (id, text) in self.texts.iter() {
+            if text.read().await.name().contains(arg) { found.push(*id) }
+        }
+        match (found.as_slice(), id) {
+            ([id], _) => Ok(*id),
+            ([], Some(id)) => bail!("no buffer {}", id),
+            ([], None) => bail!("no buffer matching {}", arg),
+            _ => bail!("more than one buffer matches {}", arg),
+        }
+    }
+
+    // the buffer `by` places along from `id`, going round at the ends. from a
+    // view that isn't of a listed buffer, like a page, it counts from before the first
+    pub fn next(&self, id: BufferId, by: isize) -> Option<BufferId> {
+        let ids = self.ids();
+        let at = match ids.iter().position(|i| *i == id) {
+            Some(at) => at as isize + by,
+            None if by > 0 => by - 1,
+            None => by,
+        };
+        let at = at.rem_euclid(ids.len().max(1) as isize) as usize;
+        ids.get(at).copied()
+    }
+
+    // one line per buffer, like `3 %a+ "notes.txt"`. % marks the focused one,
+    // a the ones shown in a window and h the hidden ones
+    pub async fn list(&self, current: BufferId, shown: &[BufferId]) -> String {
+        let mut out = String::new();
+        for (id, text) in self.texts.iter() {
+            let text = text.read().await;
+            out.push_str(&format!(
+                "{:>3} {}{}{}{} \"{}\"\n",
+                id,
+                if *id == current { '%' } else { ' ' },
+                if shown.contains(id) { 'a' } else { 'h' },
+                if text.is_modified() { '+' } else { ' ' },
+                if text.read_only { '-' } else { ' ' },
+                text.name(),
+            ));
+        }
+        out
+    }
+}
+
+// paths are compared as absolute ones where the file exists
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffers(names: &[&str]) -> Buffers {
+        let mut buffers = Buffers::default();
+        for name in names {
+            let mut text = Text::empty();
+            text.path = Some(PathBuf::from(name));
+            buffers.add(text);
+        }
+        buffers
+    }
+
+    #[tokio::test]
+    async fn numeric_names() {
+        let buffers = buffers(&["notes.txt", "2024.log"]);
+        // a buffer's id comes before a name with the number in it
+        assert_eq!(buffers.find("2").await.unwrap(), 2);
+        assert_eq!(buffers.find("1").await.unwrap(), 1);
+        assert_eq!(buffers.find("2024").await.unwrap(), 2);
+        assert_eq!(buffers.find("4").await.unwrap(), 2);
+        assert_eq!(buffers.find("7").await.unwrap_err().to_string(), "no buffer 7");
+        assert_eq!(buffers.find("txt").await.unwrap(), 1);
+        assert!(buffers.find(".").await.is_err());
+    }
+
+    #[test]
+    fn next_wraps_around() {
+        let buffers = buffers(&["a", "b", "c"]);
+        assert_eq!(buffers.next(1, 1), Some(2));
+        assert_eq!(buffers.next(3, 1), Some(1));
+        assert_eq!(buffers.next(1, -1), Some(3));
+        // from a page, which has id 0
+        assert_eq!(buffers.next(0, 1), Some(1));
+        assert_eq!(buffers.next(0, -1), Some(3));
+        assert_eq!(buffers.next(0, 2), Some(2));
+        assert_eq!(Buffers::default().next(0, 1), None);
+    }
+}
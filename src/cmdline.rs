@@ -0,0 +1,576 @@
+use std::{fs, ops::Range, path::PathBuf};
+
+use anyhow::{bail, Result};
+use ratatui::layout::Constraint;
+
+use crate::{input::Command, keymap::{self, Action}, primatives::SplitDirection, tabs::TabIndex, theme, windows::{Direction, Resize}};
+
+// (name, shortest abbreviation), used for parsing and completion
+const COMMANDS: &[(&str, &str)] = &[
+    ("bdelete", "bd"),
+    ("bnext", "bn"),
+    ("bprevious", "bp"),
+    ("buffer", "b"),
+    ("buffers", "buffers"),
+    ("close", "clo"),
+    ("colorscheme", "colo"),
+    ("delete", "d"),
+    ("edit", "e"),
+    ("files", "files"),
+    ("ls", "ls"),
+    ("map", "map"),
+    ("messages", "mes"),
+    ("only", "on"),
+    ("quit", "q"),
+    ("qall", "qa"),
+    ("redo", "red"),
+    ("resize", "res"),
+    ("set", "se"),
+    ("split", "sp"),
+    ("tabclose", "tabc"),
+    ("tabedit", "tabe"),
+    ("tabmove", "tabm"),
+    ("tabnew", "tabnew"),
+    ("tabnext", "tabn"),
+    ("tabprevious", "tabp"),
+    ("undo", "u"),
+    ("unmap", "unm"),
+    ("update", "up"),
+    ("vsplit", "vs"),
+    ("wincmd", "winc"),
+    ("wall", "wa"),
+    ("wq", "wq"),
+    ("wqall", "wqa"),
+    ("write", "w"),
+    ("xall", "xa"),
+    ("xit", "x"),
+];
+
+pub const OPTIONS: &[&str] = &["borders", "encoding", "readonly", "statusline"];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CmdLineEdit {
+    Insert(char),
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    HistoryPrev,
+    HistoryNext,
+    Complete,
+    Submit,
+    Cancel,
+}
+
+#[derive(Default, Clone)]
+pub struct CommandLine {
+    pub active: bool,
+    pub input: String,
+    pub cursor: usize, // in characters
+    history: Vec<String>,
+    history_index: Option<usize>,
+    stash: String, // what was typed before walking back through history
+    completions: Vec<String>,
+    completion_index: Option<usize>,
+    completion_start: usize,
+}
+
+impl CommandLine {
+    pub fn open(&mut self) {
+        self.active = true;
+        self.input.clear();
+        self.cursor = 0;
+        self.history_index = None;
+        self.reset_completion();
+    }
+
+    pub fn close(&mut self) {
+        self.active = false;
+    }
+
+    fn byte_index(&self, index: usize) -> usize {
+        self.input
+            .char_indices()
+            .nth(index)
+            .map(|(i, _)| i)
+            .unwrap_or(self.input.len())
+    }
+
+    fn len(&self) -> usize {
+        self.input.chars().count()
+    }
+
+    fn reset_completion(&mut self) {
+        self.completions.clear();
+        self.completion_index = None;
+    }
+
+    // returns the submitted line on Submit
+    pub fn edit(&mut self, edit: CmdLineEdit) -> Option<String> {
+        if edit != CmdLineEdit::Complete { self.reset_completion() }
+        match edit {
+            CmdLineEdit::Insert(ch) => {
+                let at = self.byte_index(self.cursor);
+                self.input.insert(at, ch);
+                self.cursor += 1;
+            },
+            CmdLineEdit::Backspace => {
+                if self.cursor == 0 { return None }
+                self.cursor -= 1;
+                let at = self.byte_index(self.cursor);
+                self.input.remove(at);
+            },
+            CmdLineEdit::Delete => {
+                if self.cursor >= self.len() { return None }
+                let at = self.byte_index(self.cursor);
+                self.input.remove(at);
+            },
+            CmdLineEdit::Left => self.cursor = self.cursor.saturating_sub(1),
+            CmdLineEdit::Right => self.cursor = (self.cursor + 1).min(self.len()),
+            CmdLineEdit::Home => self.cursor = 0,
+            CmdLineEdit::End => self.cursor = self.len(),
+            CmdLineEdit::HistoryPrev => {
+                if self.history.is_empty() { return None }
+                let index = match self.history_index {
+                    None => {
+                        self.stash = self.input.clone();
+                        self.history.len() - 1
+                    },
+                    Some(i) => i.saturating_sub(1),
+                };
+                self.history_index = Some(index);
+                self.input = self.history[index].clone();
+                self.cursor = self.len();
+            },
+            CmdLineEdit::HistoryNext => {
+                let index = self.history_index?;
+                if index + 1 < self.history.len() {
+                    self.history_index = Some(index + 1);
+                    self.input = self.history[index + 1].clone();
+                } else {
+                    self.history_index = None;
+                    self.input = std::mem::take(&mut self.stash);
+                }
+                self.cursor = self.len();
+            },
+            CmdLineEdit::Complete => self.complete(),
+            CmdLineEdit::Submit => {
+                let line = std::mem::take(&mut self.input);
+                if !line.trim().is_empty() && self.history.last() != Some(&line) {
+                    self.history.push(line.clone());
+                }
+                self.close();
+                return Some(line)
+            },
+            CmdLineEdit::Cancel => self.close(),
+        }
+        None
+    }
+
+    // tab cycles through every candidate for the word under the cursor
+    fn complete(&mut self) {
+        if self.completion_index.is_none() {
+            let before: String = self.input.chars().take(self.cursor).collect();
+            let word_start = before.rfind(' ').map(|i| i + 1).unwrap_or(0);
+            let word = &before[word_start..];
+            self.completions = if word_start == 0 {
+                let (_, word) = split_range(word);
+                COMMANDS.iter()
+                    .map(|(name, _)| name.to_string())
+                    .filter(|name| name.starts_with(word))
+                    .map(|name| format!("{}{}", &before[..before.len() - word.len()], name))
+                    .collect()
+            } else if before.trim_start().starts_with("se") {
+                OPTIONS.iter()
+                    .filter(|name| name.starts_with(word))
+                    .map(|name| name.to_string())
+                    .collect()
+            } else if before.trim_start().starts_with("colo") {
+                theme::names().into_iter().filter(|name| name.starts_with(word)).collect()
+            } else {
+                complete_path(word)
+            };
+            if self.completions.is_empty() { return }
+            self.completion_start = if word_start == 0 { 0 } else { before[..word_start].chars().count() };
+        }
+
+        let index = self.completion_index.map(|i| (i + 1) % self.completions.len()).unwrap_or(0);
+        self.completion_index = Some(index);
+
+        let start = self.byte_index(self.completion_start);
+        let end = self.byte_index(self.cursor);
+        self.input.replace_range(start..end, &self.completions[index]);
+        self.cursor = self.completion_start + self.completions[index].chars().count();
+    }
+}
+
+fn complete_path(word: &str) -> Vec<String> {
+    let (dir, file) = match word.rfind('/') {
+        Some(i) => (&word[..i + 1], &word[i + 1..]),
+        None => ("", word),
+    };
+    let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { dir }) else { return Vec::new() };
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(file) || (name.starts_with('.') && !file.starts_with('.')) { return None }
+            let slash = if entry.file_type().ok()?.is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, slash))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+// a line address: a line number, `.` or `$`, followed by any number of +/- offsets
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Address {
+    Line(usize, isize), // one based
+    Current(isize),
+    Last(isize),
+}
+
+impl Address {
+    fn resolve(&self, current: usize, last: usize) -> usize {
+        let (base, offset) = match *self {
+            Address::Line(line, offset) => (line.saturating_sub(1), offset),
+            Address::Current(offset) => (current, offset),
+            Address::Last(offset) => (last, offset),
+        };
+        base.saturating_add_signed(offset).min(last)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LineRange {
+    pub start: Address,
+    pub end: Address,
+}
+
+impl LineRange {
+    pub fn current() -> LineRange {
+        LineRange { start: Address::Current(0), end: Address::Current(0) }
+    }
+
+    // zero based and end exclusive, given the current and last rows
+    pub fn resolve(&self, current: usize, last: usize) -> Range<usize> {
+        let (start, end) = (self.start.resolve(current, last), self.end.resolve(current, last));
+        start.min(end)..start.max(end) + 1
+    }
+}
+
+fn parse_offsets(input: &str) -> (isize, &str) {
+    let mut offset = 0;
+    let mut rest = input;
+    while let Some(sign) = rest.chars().next().filter(|ch| *ch == '+' || *ch == '-') {
+        let digits: String = rest[1..].chars().take_while(|ch| ch.is_ascii_digit()).collect();
+        let n = digits.parse::<isize>().unwrap_or(1);
+        offset += if sign == '+' { n } else { -n };
+        rest = &rest[1 + digits.len()..];
+    }
+    (offset, rest)
+}
+
+fn parse_address(input: &str) -> Option<(Address, &str)> {
+    let digits: String = input.chars().take_while(|ch| ch.is_ascii_digit()).collect();
+    let (make, rest): (fn(isize) -> Address, &str) = if !digits.is_empty() {
+        let line = digits.parse().ok()?;
+        let (offset, rest) = parse_offsets(&input[digits.len()..]);
+        return Some((Address::Line(line, offset), rest))
+    } else if let Some(rest) = input.strip_prefix('.') {
+        (Address::Current, rest)
+    } else if let Some(rest) = input.strip_prefix('$') {
+        (Address::Last, rest)
+    } else if input.starts_with(['+', '-']) {
+        (Address::Current, input)
+    } else {
+        return None
+    };
+    let (offset, rest) = parse_offsets(rest);
+    Some((make(offset), rest))
+}
+
+fn parse_range(input: &str) -> Option<(LineRange, &str)> {
+    if let Some(rest) = input.strip_prefix('%') {
+        return Some((LineRange { start: Address::Line(1, 0), end: Address::Last(0) }, rest))
+    }
+    let (start, rest) = parse_address(input)?;
+    match rest.strip_prefix(',').and_then(parse_address) {
+        Some((end, rest)) => Some((LineRange { start, end }, rest)),
+        None => Some((LineRange { start, end: start }, rest)),
+    }
+}
+
+// the range prefix, if any, and what's left after it
+fn split_range(input: &str) -> (Option<LineRange>, &str) {
+    match parse_range(input) {
+        Some((range, rest)) => (Some(range), rest),
+        None => (None, input),
+    }
+}
+
+fn expand(name: &str) -> Option<&'static str> {
+    COMMANDS.iter()
+        .find(|(full, short)| full.starts_with(name) && name.starts_with(short))
+        .map(|(full, _)| *full)
+}
+
+pub fn parse(input: &str) -> Result<Vec<Command>> {
+    let input = input.trim().trim_start_matches(':');
+    if input.is_empty() { return Ok(Vec::new()) }
+
+    let (range, rest) = split_range(input);
+    let rest = rest.trim_start();
+    let name_len = rest.chars().take_while(|ch| ch.is_ascii_alphabetic()).count();
+    let (name, rest) = rest.split_at(name_len);
+    let (force, arg) = match rest.strip_prefix('!') {
+        Some(arg) => (true, arg.trim()),
+        None => (false, rest.trim()),
+    };
+    let path = if arg.is_empty() { None } else { Some(PathBuf::from(arg)) };
+
+    if name.is_empty() {
+        return match range {
+            Some(range) if arg.is_empty() => Ok(vec![Command::GotoLine(range)]),
+            _ => bail!("not an editor command: {}", input),
+        }
+    }
+    let Some(name) = expand(name) else { bail!("not an editor command: {}", input) };
+    if range.is_some() && name != "delete" { bail!("{} doesn't take a range", name) }
+
+    let quit = if force { Command::ShutDown } else { Command::Quit };
+    Ok(match name {
+        "delete" => vec![Command::DeleteLines(range.unwrap_or(LineRange::current()))],
+        "write" => vec![path.map(Command::WriteAs).unwrap_or(Command::Write { force })],
+        "wall" => vec![Command::WriteAll],
+        "quit" | "qall" => vec![quit],
+        "wq" => vec![path.map(Command::WriteAs).unwrap_or(Command::Write { force }), quit],
+        "update" => vec![Command::Update],
+        "xit" => vec![path.map(Command::WriteAs).unwrap_or(Command::Update), quit],
+        "wqall" | "xall" => vec![Command::WriteQuit],
+        "edit" => match path {
+            Some(path) => vec![Command::Edit { path, force }],
+            None => bail!("edit needs a file name"),
+        },
+        "split" => vec![Command::Split(SplitDirection::Horizontal, path)],
+        "vsplit" => vec![Command::Split(SplitDirection::Vertical, path)],
+        "close" => vec![Command::CloseWindow { force }],
+        "only" => vec![Command::OnlyWindow { force }],
+        "wincmd" => vec![match arg {
+            "h" => Command::FocusWindow(Direction::Left),
+            "j" => Command::FocusWindow(Direction::Down),
+            "k" => Command::FocusWindow(Direction::Up),
+            "l" => Command::FocusWindow(Direction::Right),
+            "s" => Command::Split(SplitDirection::Horizontal, None),
+            "v" => Command::Split(SplitDirection::Vertical, None),
+            "c" => Command::CloseWindow { force },
+            "o" => Command::OnlyWindow { force },
+            _ => bail!("wincmd takes one of h j k l s v c o"),
+        }],
+        "resize" => vec![match arg {
+            "" => Command::Equalize,
+            arg => Command::ResizeWindow(parse_size(arg)?),
+        }],
+        "messages" => match arg {
+            "" => vec![Command::Messages { clear: false }],
+            "clear" => vec![Command::Messages { clear: true }],
+            _ => bail!("messages takes nothing or clear"),
+        },
+        "buffers" | "files" | "ls" => vec![Command::ListBuffers],
+        "buffer" => match arg {
+            "" => bail!("buffer needs a number or a name"),
+            arg => vec![Command::SwitchBuffer(arg.to_string())],
+        },
+        "bnext" => vec![Command::CycleBuffer(1)],
+        "bprevious" => vec![Command::CycleBuffer(-1)],
+        "bdelete" => vec![Command::DeleteBuffer { buffer: (!arg.is_empty()).then(|| arg.to_string()), force }],
+        "tabnew" | "tabedit" => vec![Command::TabNew(path)],
+        "tabclose" => vec![Command::TabClose { force }],
+        // tabs are numbered from one here, like in the tab bar
+        "tabnext" => vec![Command::SwitchTab(match arg {
+            "" => TabIndex::Relative(1),
+            arg => TabIndex::Absolute(tab_number(arg)?.saturating_sub(1)),
+        })],
+        "tabprevious" => vec![Command::SwitchTab(TabIndex::Relative(-1))],
+        // but tabmove counts the tabs it goes after, so 0 is first
+        "tabmove" => vec![Command::MoveTab(match arg {
+            "" => TabIndex::Absolute(usize::MAX),
+            arg if arg.starts_with(['+', '-']) => TabIndex::Relative(match arg.parse() {
+                Ok(by) => by,
+                Err(_) => bail!("not a tab number: {}", arg),
+            }),
+            arg => TabIndex::Absolute(tab_number(arg)?),
+        })],
+        // map lists the bindings, of one mode when it's given, or binds a key
+        "map" => match arg.splitn(3, char::is_whitespace).collect::<Vec<_>>()[..] {
+            [""] => vec![Command::ListKeys(None)],
+            [mode] => vec![Command::ListKeys(Some(keymap::mode(mode)?))],
+            [mode, keys, action] => vec![Command::MapKey(keymap::mode(mode)?, keys.to_string(), match action.trim() {
+                keymap::UNBIND => None,
+                action => Some(Action::parse(action)?),
+            })],
+            _ => bail!("map takes a mode, keys and what they do"),
+        },
+        "unmap" => match arg.split_whitespace().collect::<Vec<_>>()[..] {
+            [mode, keys] => vec![Command::MapKey(keymap::mode(mode)?, keys.to_string(), None)],
+            _ => bail!("unmap takes a mode and keys"),
+        },
+        "colorscheme" => vec![Command::ColorScheme((!arg.is_empty()).then(|| arg.to_string()))],
+        "undo" => vec![Command::Undo],
+        "redo" => vec![Command::Redo],
+        "set" => arg.split_whitespace().map(parse_option).collect::<Result<_>>()?,
+        _ => bail!("not an editor command: {}", input),
+    })
+}
+
+fn tab_number(arg: &str) -> Result<usize> {
+    match arg.parse() {
+        Ok(n) => Ok(n),
+        Err(_) => bail!("not a tab number: {}", arg),
+    }
+}
+
+// +N and -N grow and shrink the pane. N, N%, A/B, min:N and max:N set its size,
+// and fill:N gives it N shares of whatever's left over
+fn parse_size(arg: &str) -> Result<Resize> {
+    let number = |n: &str| match n.parse::<u16>() {
+        Ok(n) => Ok(n),
+        Err(_) => bail!("not a size: {}", arg),
+    };
+    if let Some(n) = arg.strip_prefix('+') { return Ok(Resize::By(number(n)? as i16)) }
+    if let Some(n) = arg.strip_prefix('-') { return Ok(Resize::By(-(number(n)? as i16))) }
+    if let Some(n) = arg.strip_suffix('%') { return Ok(Resize::To(Constraint::Percentage(number(n)?.min(100)))) }
+    if let Some(n) = arg.strip_prefix("min:") { return Ok(Resize::To(Constraint::Min(number(n)?))) }
+    if let Some(n) = arg.strip_prefix("max:") { return Ok(Resize::To(Constraint::Max(number(n)?))) }
+    if let Some(n) = arg.strip_prefix("fill:") { return Ok(Resize::To(Constraint::Fill(number(n)?.max(1)))) }
+    if let Some((a, b)) = arg.split_once('/') {
+        let (a, b) = (number(a)? as u32, number(b)? as u32);
+        if a == 0 || a > b { bail!("not a size: {}", arg) }
+        return Ok(Resize::To(Constraint::Ratio(a, b)))
+    }
+    Ok(Resize::To(Constraint::Length(number(arg)?)))
+}
+
+// `name=value`, `name` to switch on and `noname` to switch off
+fn parse_option(arg: &str) -> Result<Command> {
+    if let Some((name, value)) = arg.split_once('=') {
+        return Ok(Command::Set(name.to_string(), value.to_string()))
+    }
+    if OPTIONS.contains(&arg) {
+        return Ok(Command::Set(arg.to_string(), "true".to_string()))
+    }
+    match arg.strip_prefix("no") {
+        Some(name) if OPTIONS.contains(&name) => Ok(Command::Set(name.to_string(), "false".to_string())),
+        _ => bail!("unknown option: {}", arg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: Address, end: Address) -> LineRange {
+        LineRange { start, end }
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_range("5"), Some((range(Address::Line(5, 0), Address::Line(5, 0)), "")));
+        assert_eq!(parse_range("%d"), Some((range(Address::Line(1, 0), Address::Last(0)), "d")));
+        assert_eq!(parse_range(".,$-2d"), Some((range(Address::Current(0), Address::Last(-2)), "d")));
+        assert_eq!(parse_range("+3,++"), Some((range(Address::Current(3), Address::Current(2)), "")));
+        assert_eq!(parse_range("2-1,4"), Some((range(Address::Line(2, -1), Address::Line(4, 0)), "")));
+        assert_eq!(parse_range("d"), None);
+
+        // reversed ranges are turned round, and everything stays inside the text
+        assert_eq!(range(Address::Line(4, 0), Address::Line(2, 0)).resolve(0, 9), 1..4);
+        assert_eq!(range(Address::Current(-5), Address::Last(3)).resolve(2, 9), 0..10);
+        assert_eq!(LineRange::current().resolve(3, 9), 3..4);
+    }
+
+    #[test]
+    fn abbreviations() {
+        assert_eq!(expand("w"), Some("write"));
+        assert_eq!(expand("wri"), Some("write"));
+        assert_eq!(expand("q"), Some("quit"));
+        assert_eq!(expand("qa"), Some("qall"));
+        assert_eq!(expand("red"), Some("redo"));
+        assert_eq!(expand("re"), None);
+        assert_eq!(expand("tabnewer"), None);
+        assert!(parse("sp").unwrap() == vec![Command::Split(SplitDirection::Horizontal, None)]);
+        assert!(parse("vs foo").unwrap() == vec![Command::Split(SplitDirection::Vertical, Some(PathBuf::from("foo")))]);
+        assert!(parse("frobnicate").is_err());
+    }
+
+    #[test]
+    fn commands_with_ranges() {
+        let lines = range(Address::Line(2, 0), Address::Line(4, 0));
+        assert!(parse(":2,4d").unwrap() == vec![Command::DeleteLines(lines)]);
+        assert!(parse("d").unwrap() == vec![Command::DeleteLines(LineRange::current())]);
+        assert!(parse("$").unwrap() == vec![Command::GotoLine(range(Address::Last(0), Address::Last(0)))]);
+        assert!(parse("2,4w").is_err());
+        assert!(parse("2 foo").is_err());
+        assert!(parse("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn force() {
+        assert!(parse("q").unwrap() == vec![Command::Quit]);
+        assert!(parse("q!").unwrap() == vec![Command::ShutDown]);
+        assert!(parse("w").unwrap() == vec![Command::Write { force: false }]);
+        assert!(parse("w!").unwrap() == vec![Command::Write { force: true }]);
+        assert!(parse("wq!").unwrap() == vec![Command::Write { force: true }, Command::ShutDown]);
+        assert!(parse("x").unwrap() == vec![Command::Update, Command::Quit]);
+        assert!(parse("clo!").unwrap() == vec![Command::CloseWindow { force: true }]);
+        assert!(parse("e! notes").unwrap() == vec![Command::Edit { path: PathBuf::from("notes"), force: true }]);
+        assert!(parse("bd!").unwrap() == vec![Command::DeleteBuffer { buffer: None, force: true }]);
+        assert!(parse("bd 3").unwrap() == vec![Command::DeleteBuffer { buffer: Some("3".to_string()), force: false }]);
+    }
+
+    #[test]
+    fn options() {
+        let set = |name: &str, value: &str| Command::Set(name.to_string(), value.to_string());
+        assert!(parse("set encoding=utf-16le").unwrap() == vec![set("encoding", "utf-16le")]);
+        assert!(parse("se readonly noborders").unwrap() == vec![set("readonly", "true"), set("borders", "false")]);
+        assert!(parse("set statusline=%f").unwrap() == vec![set("statusline", "%f")]);
+        assert!(parse("set bogus").is_err());
+        assert!(parse("set nobogus").is_err());
+    }
+
+    #[test]
+    fn sizes() {
+        assert!(parse_size("+2").unwrap() == Resize::By(2));
+        assert!(parse_size("-1").unwrap() == Resize::By(-1));
+        assert!(parse_size("30%").unwrap() == Resize::To(Constraint::Percentage(30)));
+        assert!(parse_size("1/3").unwrap() == Resize::To(Constraint::Ratio(1, 3)));
+        assert!(parse_size("12").unwrap() == Resize::To(Constraint::Length(12)));
+        assert!(parse_size("4/3").is_err());
+        assert!(parse_size("big").is_err());
+    }
+
+    #[test]
+    fn completion() {
+        let mut line = CommandLine::default();
+        "tabn".chars().for_each(|ch| { line.edit(CmdLineEdit::Insert(ch)); });
+        line.edit(CmdLineEdit::Complete);
+        assert_eq!(line.input, "tabnew");
+        line.edit(CmdLineEdit::Complete);
+        assert_eq!(line.input, "tabnext");
+        line.edit(CmdLineEdit::Complete);
+        assert_eq!(line.input, "tabnew");
+
+        let mut line = CommandLine::default();
+        "2,3de".chars().for_each(|ch| { line.edit(CmdLineEdit::Insert(ch)); });
+        line.edit(CmdLineEdit::Complete);
+        assert_eq!(line.input, "2,3delete");
+
+        let mut line = CommandLine::default();
+        "set enc".chars().for_each(|ch| { line.edit(CmdLineEdit::Insert(ch)); });
+        line.edit(CmdLineEdit::Complete);
+        assert_eq!(line.input, "set encoding");
+        assert_eq!(line.cursor, 12);
+    }
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashMap, env, fs, path::{Path, PathBuf}, sync::OnceLock};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use toml::Table;
+
+use crate::{keymap::Keymaps, primatives::SplitDirection, status, theme};
+
+//
+// settings read once at startup. the user's config is read first, then any
+// project config in the working directory or above it, each overriding the
+// last. a file with mistakes in it is left out and the mistakes are reported.
+// project files come with whatever was checked out, so they can't bind keys,
+// which could run any command line
+//
+
+const PROJECT_FILE: &str = ".bespoke-ed.toml";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub fps_limit: u64,
+    pub gutter: u16, // columns for line numbers, 0 hides them
+    pub split: SplitDirection, // how the files on the command line are laid out
+    pub borders: bool,
+    pub mouse: bool, // off leaves the mouse to the terminal, for selecting and copying
+    pub statusline: String,
+    pub theme: String,
+    pub colors: String, // auto, 16, 256 or truecolor
+    pub keys: HashMap<String, HashMap<String, String>>, // mode, then keys to action
+    pub leader: String, // the key `<leader>` stands for in bindings
+    pub key_timeout: u64, // milliseconds to wait for the rest of a binding
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            fps_limit: 60,
+            gutter: 4,
+            split: SplitDirection::Vertical,
+            borders: false,
+            mouse: true,
+            statusline: status::DEFAULT_SEGMENTS.to_string(),
+            theme: "default".to_string(),
+            colors: "auto".to_string(),
+            keys: HashMap::new(),
+            leader: "\\".to_string(),
+            key_timeout: 1000,
+        }
+    }
+}
+
+impl Config {
+    fn validate(&self) -> Result<()> {
+        if !(1..=240).contains(&self.fps_limit) { bail!("fps_limit has to be between 1 and 240") }
+        if self.gutter > 16 { bail!("gutter can't be wider than 16") }
+        status::parse(&self.statusline)?;
+        if !theme::DEPTHS.contains(&self.colors.as_str()) { bail!("colors has to be one of {}", theme::DEPTHS.join(", ")) }
+        theme::load(&self.theme)?;
+        if self.key_timeout == 0 { bail!("key_timeout has to be at least 1") }
+        Keymaps::with(self)?;
+        Ok(())
+    }
+}
+
+// the settings everything reads, the defaults until `init` has run
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+// `path` stands in for the user's config when it's given. returns the
+// settings along with whatever was wrong with the files they came from
+pub fn load(path: Option<&Path>) -> (Config, Vec<String>) {
+    // (path, required, project)
+    let mut files = Vec::new();
+    match path {
+        Some(path) => files.push((path.to_path_buf(), true, false)),
+        None => files.extend(user_file().map(|path| (path, false, false))),
+    }
+    files.extend(project_file().map(|path| (path, false, true)));
+    load_files(files)
+}
+
+fn load_files(files: Vec<(PathBuf, bool, bool)>) -> (Config, Vec<String>) {
+    let mut merged = Table::new();
+    let mut errors = Vec::new();
+    for (path, required, project) in files {
+        if !required && !path.exists() { continue }
+        let mut layer = match read(&path) {
+            Ok(layer) => layer,
+            Err(e) => { errors.push(format!("{:#}", e)); continue },
+        };
+        if project && layer.remove("keys").is_some() {
+            errors.push(format!("{}: keys can only be bound in the user's config", path.display()));
+        }
+        // a layer is only kept if the settings still make sense with it
+        let mut next = merged.clone();
+        merge(&mut next, layer);
+        match parse(next.clone()) {
+            Ok(_) => merged = next,
+            Err(e) => errors.push(format!("{}: {:#}", path.display(), e)),
+        }
+    }
+    (parse(merged).unwrap_or_default(), errors)
+}
+
+fn read(path: &Path) -> Result<Table> {
+    let content = fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    content.parse().with_context(|| format!("{} isn't valid toml", path.display()))
+}
+
+fn parse(table: Table) -> Result<Config> {
+    let config = Config::deserialize(table)?;
+    config.validate()?;
+    Ok(config)
+}
+
+// tables are merged key by key, anything else in `layer` replaces what was there
+fn merge(base: &mut Table, layer: Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(layer)) => merge(base, layer),
+            (_, value) => { base.insert(key, value); },
+        }
+    }
+}
+
+// where the user's config and themes live
+pub fn dir() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("bespoke-ed"))
+}
+
+fn user_file() -> Option<PathBuf> {
+    Some(dir()?.join("config.toml"))
+}
+
+// the nearest one, going up from the working directory
+fn project_file() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_keys_left_out() {
+        let dir = env::temp_dir().join(format!("bespoke-ed-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let user = dir.join("config.toml");
+        let project = dir.join(PROJECT_FILE);
+        fs::write(&user, "gutter = 2\nmouse = false\n[keys.normal]\nx = \"write\"\n").unwrap();
+        fs::write(&project, "gutter = 6\nborders = true\n[keys.normal]\ny = \"quit\"\n").unwrap();
+
+        let (config, errors) = load_files(vec![(user, true, false), (project.clone(), false, true)]);
+        // the rest of the project's settings still apply over the user's
+        assert_eq!(config.gutter, 6);
+        assert!(config.borders);
+        assert!(!config.mouse);
+        assert_eq!(config.keys["normal"].keys().collect::<Vec<_>>(), vec!["x"]);
+        assert_eq!(errors, vec![format!("{}: keys can only be bound in the user's config", project.display())]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,41 +1,559 @@
-use tokio::{sync::{mpsc::UnboundedReceiver, RwLock}, task::JoinHandle};
+use std::{collections::VecDeque, path::PathBuf, sync::Arc};
 
-use crate::{input::Command, primatives::Root, zipper::{DynZipper, RootZipper}, State};
+use anyhow::{bail, Result};
+use tokio::{sync::{mpsc::UnboundedReceiver, Notify, RwLock}, task::JoinHandle};
+
+use crate::{
+    cmdline::{self, CmdLineEdit},
+    encoding::Encoding,
+    input::Command,
+    keymap::Keymaps,
+    messages::Level,
+    mouse,
+    buffers::BufferId,
+    primatives::{descendant_texts, descendant_views, Root, SplitDirection, Text, View, Window},
+    tabs::Tabs,
+    zipper::{self, DynZipper, RootZipper},
+    status, theme, windows, State, ARW
+};
 
 
 pub fn control_thread_init(
     state: &'static RwLock<State>,
-    root: &'static RwLock<Root>,
+    tabs: &'static RwLock<Tabs>,
+    keymaps: &'static RwLock<Keymaps>,
+    redraw: &'static Notify,
     mut input_rx: UnboundedReceiver<Command>,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
-        let mut zipper: DynZipper = Box::new(RootZipper::new(root).await);
+        let mut zipper: DynZipper = Box::new(RootZipper::new(&tabs.read().await.root()).await);
+        // where the focus was left in each tab, the current one's is taken out
+        let mut parked: Vec<Option<DynZipper>> = vec![None];
+        let mut drag = None;
+        // anything posted before the editor started, like mistakes in the config
+        if let Some(z) = page_messages(tabs, &zipper).await { zipper = z }
+        publish_status(tabs, State::Normal, &zipper).await;
 
         while let Some(msg) = input_rx.recv().await {
-            match msg {
-                Command::Insert(_) => (),
-                Command::NormalMode => *state.write().await = State::Normal,
-                Command::InsertMode => *state.write().await = State::Insert,
-                Command::TravelMode => *state.write().await = State::Travel,
-                Command::ToFirstChild => zipper = zipper.child(0).await,
-                Command::ToParent => zipper = zipper.parent().await,
-                Command::ToLeftSibling => {
-                    // clear_tx.send(ClearScreenMsg).unwrap();
-                    zipper = zipper.move_left().await
-                },
-                Command::ToRightSibling => {
-                    // clear_tx.send(ClearScreenMsg).unwrap();
-                    zipper = zipper.move_right().await
-                },
-                Command::Reset => (),
-                Command::ShutDown => *state.write().await = State::ShutDown,
-                Command::PrevChar => (),
-                Command::PrevLine => (),
-                Command::NextLine => (),
-                Command::NextChar => (),
-                Command::ToLastChild => (),
-                Command::ToMiddleChild => (),
+            // half typed bindings are only shown, they leave the messages be
+            if let Command::PendingKeys(keys) = msg {
+                tabs.write().await.pending = keys;
+                redraw.notify_one();
+                continue
             }
+            tabs.write().await.messages.clear();
+            // the command line can expand into several commands
+            let mut queue = VecDeque::from([msg]);
+            while let Some(msg) = queue.pop_front() {
+                let root = tabs.read().await.root();
+                // a selection lasts until the next thing done, unless that uses it
+                let keeps_selection = matches!(
+                    msg,
+                    Command::Mouse(_) | Command::TerminalResized(_) | Command::DeleteSelection | Command::Quit
+                );
+                if !keeps_selection { take_selection(&zipper).await; }
+                match msg {
+                    Command::Insert(ch) => match zipper::insert(&zipper, &ch.to_string()).await {
+                        Some(z) => zipper = z,
+                        None => read_only_warning(tabs, &zipper).await,
+                    },
+                    // terminals send line breaks in pastes as carriage returns
+                    Command::Paste(content) => match zipper::insert(&zipper, &content.replace("\r\n", "\n").replace('\r', "\n")).await {
+                        Some(z) => zipper = z,
+                        None => read_only_warning(tabs, &zipper).await,
+                    },
+                    Command::Backspace => match zipper::backspace(&zipper).await {
+                        Some(z) => zipper = z,
+                        None => read_only_warning(tabs, &zipper).await,
+                    },
+                    Command::NormalMode => *state.write().await = State::Normal,
+                    Command::InsertMode => *state.write().await = State::Insert,
+                    Command::TravelMode => *state.write().await = State::Travel,
+                    Command::ToFirstChild => zipper = zipper.child(0).await,
+                    Command::ToParent => zipper = zipper.parent().await,
+                    Command::ToLeftSibling => {
+                        // clear_tx.send(ClearScreenMsg).unwrap();
+                        zipper = zipper.move_left().await
+                    },
+                    Command::ToRightSibling => {
+                        // clear_tx.send(ClearScreenMsg).unwrap();
+                        zipper = zipper.move_right().await
+                    },
+                    Command::Reset => (),
+                    Command::Quit => {
+                        // the Esc that would quit drops a selection instead
+                        if take_selection(&zipper).await.is_some() { continue }
+                        let mut modified = 0;
+                        for text in all_texts(tabs).await {
+                            if text.read().await.is_modified() { modified += 1 }
+                        }
+                        if modified == 0 {
+                            *state.write().await = State::ShutDown
+                        } else {
+                            tabs.write().await.post(Level::Warning, format!(
+                                "{} buffer(s) have unsaved changes. W to write all and quit, Q to quit anyway",
+                                modified
+                            ));
+                        }
+                    },
+                    Command::WriteQuit => match write(modified(all_texts(tabs).await).await, false).await {
+                        Ok(()) => *state.write().await = State::ShutDown,
+                        Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                    },
+                    Command::ShutDown => *state.write().await = State::ShutDown,
+                    Command::PrevChar => if let Some(z) = zipper::step(&zipper, 0, -1).await { zipper = z },
+                    Command::PrevLine => if let Some(z) = zipper::step(&zipper, -1, 0).await { zipper = z },
+                    Command::NextLine => if let Some(z) = zipper::step(&zipper, 1, 0).await { zipper = z },
+                    Command::NextChar => if let Some(z) = zipper::step(&zipper, 0, 1).await { zipper = z },
+                    Command::ToLastChild => (),
+                    Command::ToMiddleChild => (),
+                    Command::Write { force } => {
+                        if let Err(e) = write(zipper.texts().await, force).await {
+                            tabs.write().await.post(Level::Error, format!("{:#}", e));
+                        }
+                    },
+                    // only what changed since it was last written
+                    Command::Update => {
+                        if let Err(e) = write(modified(zipper.texts().await).await, false).await {
+                            tabs.write().await.post(Level::Error, format!("{:#}", e));
+                        }
+                    },
+                    Command::WriteAll => {
+                        if let Err(e) = write(modified(all_texts(tabs).await).await, false).await {
+                            tabs.write().await.post(Level::Error, format!("{:#}", e));
+                        }
+                    },
+                    Command::ExMode => {
+                        tabs.write().await.cmdline.open();
+                        *state.write().await = State::Command;
+                    },
+                    Command::CmdLine(edit) => {
+                        if edit == CmdLineEdit::Submit || edit == CmdLineEdit::Cancel {
+                            *state.write().await = State::Normal;
+                        }
+                        let Some(line) = tabs.write().await.cmdline.edit(edit) else { continue };
+                        match cmdline::parse(&line) {
+                            Ok(commands) => queue.extend(commands),
+                            Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                        }
+                    },
+                    Command::Ex(line) => match cmdline::parse(&line) {
+                        Ok(commands) => queue.extend(commands),
+                        Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                    },
+                    Command::ListKeys(state) => {
+                        let list = keymaps.read().await.list(state);
+                        tabs.write().await.post(Level::Info, list.trim_end());
+                    },
+                    Command::MapKey(state, keys, action) => {
+                        if let Err(e) = keymaps.write().await.map(state, &keys, action) {
+                            tabs.write().await.post(Level::Error, format!("{:#}", e));
+                        }
+                    },
+                    Command::ColorScheme(None) => tabs.write().await.post(Level::Info, theme::theme().name.clone()),
+                    Command::ColorScheme(Some(name)) => match theme::load(&name) {
+                        Ok(loaded) => theme::set(loaded),
+                        Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                    },
+                    Command::Mouse(event) => {
+                        // the focus can't move out from under the command line
+                        if *state.read().await == State::Command { continue }
+                        if let Some(z) = mouse::handle(&root, &zipper, event, &mut drag).await { zipper = z }
+                    },
+                    Command::CloseWindow { force } => match windows::close(&root, &zipper, force).await {
+                        Ok(z) => zipper = z,
+                        Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                    },
+                    Command::OnlyWindow { force } => match windows::only(&root, &zipper, force).await {
+                        Ok(z) => zipper = z,
+                        Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                    },
+                    Command::FocusWindow(direction) => {
+                        if let Some(z) = windows::focus(&root, &zipper, direction).await { zipper = z }
+                    },
+                    Command::ResizeWindow(resize) => {
+                        if let Err(e) = windows::resize(&root, &zipper, resize).await {
+                            tabs.write().await.post(Level::Error, format!("{:#}", e));
+                        }
+                    },
+                    Command::Equalize => windows::equalize(&root).await,
+                    Command::TerminalResized(area) => {
+                        tabs.write().await.area = area;
+                        tabs.read().await.layout().await;
+                        if let Some(z) = zipper::refocus(&zipper).await { zipper = z }
+                    },
+                    Command::TabNew(path) => match new_tab(tabs, path).await {
+                        Ok(index) => {
+                            parked.insert(index, None);
+                            zipper = switch_tab(tabs, &mut parked, zipper, index).await;
+                        },
+                        Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                    },
+                    Command::TabClose { force } => match close_tab(tabs, force).await {
+                        Ok(index) => {
+                            parked.remove(index);
+                            // the closed tab's zipper goes, the one taking its place is picked up
+                            let current = tabs.read().await.current;
+                            zipper = match parked[current].take() {
+                                Some(z) => z,
+                                None => enter_tab(tabs).await,
+                            };
+                            if let Some(z) = zipper::refocus(&zipper).await { zipper = z }
+                        },
+                        Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                    },
+                    Command::SwitchTab(index) => {
+                        let index = tabs.read().await.resolve(index, true);
+                        zipper = switch_tab(tabs, &mut parked, zipper, index).await;
+                    },
+                    Command::MoveTab(index) => {
+                        let mut tabs = tabs.write().await;
+                        let (from, to) = (tabs.current, tabs.resolve(index, false));
+                        let moved = tabs.roots.remove(from);
+                        tabs.roots.insert(to, moved);
+                        let moved = parked.remove(from);
+                        parked.insert(to, moved);
+                        tabs.current = to;
+                    },
+                    Command::ListBuffers => {
+                        let current = match zipper::first_text(&zipper).await {
+                            Some(text) => text.view().read().await.buffer,
+                            None => 0,
+                        };
+                        let mut shown = Vec::new();
+                        for root in tabs.read().await.roots.iter() {
+                            for view in descendant_views(root.read().await.children.clone()).await {
+                                shown.push(view.read().await.buffer);
+                            }
+                        }
+                        let mut tabs = tabs.write().await;
+                        let list = tabs.buffers.list(current, &shown).await;
+                        tabs.post(Level::Info, list.trim_end());
+                    },
+                    Command::DeleteBuffer { buffer, force } => match delete_buffer(tabs, &zipper, buffer, force).await {
+                        Ok(z) => {
+                            zipper = z;
+                            // the other tabs' trees changed under their parked zippers
+                            parked.iter_mut().for_each(|z| *z = None);
+                        },
+                        Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                    },
+                    Command::Messages { clear: true } => tabs.write().await.messages.clear_history(),
+                    Command::Messages { clear: false } => {
+                        let history = tabs.read().await.messages.history();
+                        match open_page(&root, &zipper, "[messages]", history).await {
+                            Ok(z) => zipper = z,
+                            Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                        }
+                    },
+                    Command::Set(name, value) if name == "statusline" => match status::parse(&value) {
+                        Ok(segments) => tabs.write().await.status.segments = segments,
+                        Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                    },
+                    cmd => match ex(tabs, &root, &zipper, cmd).await {
+                        Ok(Some(z)) => zipper = z,
+                        Ok(None) => (),
+                        Err(e) => tabs.write().await.post(Level::Error, format!("{:#}", e)),
+                    },
+                }
+                // a command that failed stops the ones after it, so :wq doesn't quit when the write didn't happen
+                if tabs.read().await.messages.current().is_some_and(|m| m.level == Level::Error) { queue.clear() }
+            }
+            tabs.read().await.layout().await;
+            if let Some(z) = page_messages(tabs, &zipper).await { zipper = z }
+            publish_status(tabs, *state.read().await, &zipper).await;
+            redraw.notify_one();
         }
     })
 }
+
+async fn publish_status(tabs: &'static RwLock<Tabs>, state: State, zipper: &DynZipper) {
+    let text = zipper::first_text(zipper).await;
+    let view = text.as_ref().map(|text| text.view());
+    let position = zipper.cursor().map(|(_, row, column)| (row, column));
+    let previous = tabs.read().await.status.view.clone();
+    if let Some(previous) = previous {
+        previous.write().await.focused = false;
+    }
+    if let Some(ref view) = view {
+        view.write().await.focused = true;
+    }
+    let status = &mut tabs.write().await.status;
+    status.mode = state.name();
+    status.depth = zipper.depth();
+    status.position = position;
+    status.text = text.map(|text| text.focus());
+    status.view = view;
+}
+
+// every buffer, shown in a window or not
+async fn all_texts(tabs: &'static RwLock<Tabs>) -> Vec<ARW<Text>> {
+    tabs.read().await.buffers.texts()
+}
+
+// the texts with changes to write, leaving out read-only ones
+async fn modified(texts: Vec<ARW<Text>>) -> Vec<ARW<Text>> {
+    let mut modified = Vec::new();
+    for text in texts {
+        let changed = { let text = text.read().await; text.is_modified() && !text.read_only };
+        if changed { modified.push(text) }
+    }
+    modified
+}
+
+// adds a tab after the current one, showing the file or an empty text
+async fn new_tab(tabs: &'static RwLock<Tabs>, path: Option<PathBuf>) -> Result<usize> {
+    let mut tabs = tabs.write().await;
+    let view = match path {
+        Some(path) => tabs.buffers.open(&path).await?,
+        None => tabs.buffers.add(Text::empty()),
+    };
+    let split_dir = tabs.root().read().await.split_dir;
+    let mut window = Window::new(split_dir, tabs.root_area());
+    window.add_view(view, 0);
+    let mut root = Root::new(split_dir, tabs.root_area());
+    root.children.push(Arc::new(RwLock::new(window)));
+
+    let index = tabs.current + 1;
+    tabs.roots.insert(index, Arc::new(RwLock::new(root)));
+    Ok(index)
+}
+
+// removes the current tab, returning its index
+async fn close_tab(tabs: &'static RwLock<Tabs>, force: bool) -> Result<usize> {
+    let mut tabs = tabs.write().await;
+    if tabs.roots.len() == 1 { bail!("can't close the last tab") }
+    let root = tabs.root();
+    if !force {
+        let others: Vec<_> = tabs.roots.iter().filter(|r| !Arc::ptr_eq(r, &root)).cloned().collect();
+        let mut shown = Vec::new();
+        for other in others {
+            shown.extend(descendant_texts(other.read().await.children.clone()).await);
+        }
+        // texts still open in another tab aren't lost with this one
+        for text in descendant_texts(root.read().await.children.clone()).await {
+            if shown.iter().any(|t| Arc::ptr_eq(t, &text)) { continue }
+            if text.read().await.is_modified() {
+                bail!("tab has unsaved changes, add ! to close it anyway")
+            }
+        }
+    }
+    let index = tabs.current;
+    tabs.roots.remove(index);
+    tabs.current = index.min(tabs.roots.len() - 1);
+    // the tab bar may have gone with it
+    tabs.layout().await;
+    Ok(index)
+}
+
+// parks the focus in the current tab and picks it up where it was left in `index`
+async fn switch_tab(
+    tabs: &'static RwLock<Tabs>,
+    parked: &mut [Option<DynZipper>],
+    zipper: DynZipper,
+    index: usize,
+) -> DynZipper {
+    let current = tabs.read().await.current;
+    parked[current] = Some(zipper);
+    tabs.write().await.current = index;
+    tabs.read().await.layout().await;
+    let zipper = match parked[index].take() {
+        Some(zipper) => zipper,
+        None => enter_tab(tabs).await,
+    };
+    zipper::refocus(&zipper).await.unwrap_or(zipper)
+}
+
+async fn enter_tab(tabs: &'static RwLock<Tabs>) -> DynZipper {
+    let root = tabs.read().await.root();
+    windows::enter(&root, &[0, 0]).await
+}
+
+// takes the buffer out of the list, showing another one wherever it was
+async fn delete_buffer(
+    tabs: &'static RwLock<Tabs>,
+    zipper: &DynZipper,
+    buffer: Option<String>,
+    force: bool,
+) -> Result<DynZipper> {
+    let Some(text) = zipper::first_text(zipper).await else { bail!("no buffer to delete") };
+    let mut tabs = tabs.write().await;
+    let id: BufferId = match buffer {
+        Some(arg) => tabs.buffers.find(&arg).await?,
+        None => text.view().read().await.buffer,
+    };
+    let Some(deleted) = tabs.buffers.view(id) else { bail!("this buffer isn't in the list") };
+    if !force && deleted.text.read().await.is_modified() {
+        bail!("buffer has unsaved changes, add ! to delete it anyway")
+    }
+    tabs.buffers.remove(id);
+
+    // the first buffer left takes its place, or a new empty one when it was the last
+    let replacement = match tabs.buffers.ids().first() {
+        Some(&first) => tabs.buffers.view(first).unwrap(),
+        None => tabs.buffers.add(Text::empty()),
+    };
+    zipper.highlight(false).await;
+    for root in tabs.roots.iter() {
+        windows::replace_views(root, id, || replacement.clone()).await;
+    }
+    Ok(windows::enter(&tabs.root(), &text.path()).await)
+}
+
+// scratch buffers nobody typed in are left alone, and read-only texts are
+// only written when forced
+async fn write(texts: Vec<ARW<Text>>, force: bool) -> Result<()> {
+    for text in texts {
+        let mut text = text.write().await;
+        if text.path.is_none() && !text.is_modified() { continue }
+        if text.read_only && !force { bail!("{} is read-only (add ! to write it anyway)", text.name()) }
+        text.save()?;
+    }
+    Ok(())
+}
+
+// the focused view's selection, which is gone from it afterwards
+async fn take_selection(zipper: &DynZipper) -> Option<((usize, usize), (usize, usize))> {
+    let text = zipper::first_text(zipper).await?;
+    let selection = text.view().write().await.selection.take();
+    selection
+}
+
+async fn read_only_warning(tabs: &'static RwLock<Tabs>, zipper: &DynZipper) {
+    for text in zipper.texts().await {
+        if text.read().await.read_only {
+            tabs.write().await.post(Level::Warning, "buffer is read-only");
+            return
+        }
+    }
+}
+
+// commands from the command line that work on the focused text
+async fn ex(tabs: &'static RwLock<Tabs>, root: &ARW<Root>, zipper: &DynZipper, cmd: Command) -> Result<Option<DynZipper>> {
+    let Some(text) = zipper::first_text(zipper).await else { bail!("no text to work on") };
+    let focus = text.focus();
+    let row = zipper.cursor().map(|(_, row, _)| row).unwrap_or(0);
+    let last = focus.read().await.line_count() - 1;
+
+    Ok(Some(match cmd {
+        Command::GotoLine(range) => {
+            let rows = range.resolve(row, last);
+            zipper.highlight(false).await;
+            text.line_at(rows.end - 1).await
+        },
+        Command::Undo | Command::Redo => {
+            if focus.read().await.read_only { bail!("buffer is read-only") }
+            let undo = cmd == Command::Undo;
+            let moved = if undo { focus.write().await.undo().await } else { focus.write().await.redo().await };
+            let Some((row, column)) = moved else {
+                bail!("already at {} change", if undo { "oldest" } else { "newest" })
+            };
+            zipper.highlight(false).await;
+            text.cursor_at(row, column).await
+        },
+        Command::DeleteSelection => {
+            if focus.read().await.read_only { bail!("buffer is read-only") }
+            let Some((first, last)) = take_selection(zipper).await else { bail!("nothing is selected") };
+            zipper.highlight(false).await;
+            focus.write().await.remove_chars(first, last).await;
+            text.cursor_at(first.0, first.1).await
+        },
+        Command::DeleteLines(range) => {
+            if focus.read().await.read_only { bail!("buffer is read-only") }
+            let rows = range.resolve(row, last);
+            zipper.highlight(false).await;
+            focus.write().await.remove_lines(rows.clone());
+            text.line_at(rows.start).await
+        },
+        Command::WriteAs(path) => {
+            let mut focus = focus.write().await;
+            if focus.path.is_none() {
+                focus.path = Some(path);
+                focus.save()?;
+            } else {
+                focus.write_to(&path)?;
+            }
+            return Ok(None)
+        },
+        // the buffer being left stays in the list, so nothing is lost switching away from it
+        Command::Edit { path, force } => {
+            let opened = tabs.write().await.buffers.open(&path).await?;
+            // but ! reads the file again, throwing away changes made to it since
+            if force && opened.text.read().await.is_modified() {
+                *opened.text.write().await = Text::open(&path)?;
+            }
+            windows::show(root, zipper, opened).await?
+        },
+        Command::Split(split_dir, path) => {
+            let opened = match path {
+                Some(path) => tabs.write().await.buffers.open(&path).await?,
+                None => text.view().read().await.split(),
+            };
+            windows::split(root, zipper, split_dir, opened).await?
+        },
+        Command::SwitchBuffer(arg) => {
+            let buffers = &tabs.read().await.buffers;
+            let id = buffers.find(&arg).await?;
+            windows::show(root, zipper, buffers.view(id).unwrap()).await?
+        },
+        Command::CycleBuffer(by) => {
+            let current = text.view().read().await.buffer;
+            let buffers = &tabs.read().await.buffers;
+            let Some(id) = buffers.next(current, by) else { bail!("there are no buffers") };
+            windows::show(root, zipper, buffers.view(id).unwrap()).await?
+        },
+        Command::Set(name, value) => {
+            let mut focus = focus.write().await;
+            match name.as_str() {
+                "readonly" => focus.read_only = parse_bool(&name, &value)?,
+                "borders" => text.window().write().await.borders = parse_bool(&name, &value)?,
+                "encoding" => match Encoding::from_name(&value) {
+                    Some(encoding) => focus.encoding = encoding,
+                    None => bail!("unknown encoding: {}", value),
+                },
+                _ => bail!("unknown option: {}", name),
+            }
+            return Ok(None)
+        },
+        _ => return Ok(None),
+    }))
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool> {
+    match value {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => bail!("{} takes true or false, not {}", name, value),
+    }
+}
+
+// opens the messages too long for the message line, if there are any
+async fn page_messages(tabs: &'static RwLock<Tabs>, zipper: &DynZipper) -> Option<DynZipper> {
+    let page = tabs.write().await.messages.page.take()?;
+    let root = tabs.read().await.root();
+    match open_page(&root, zipper, "[message]", page).await {
+        Ok(z) => Some(z),
+        Err(e) => { tabs.write().await.post(Level::Error, format!("{:#}", e)); None },
+    }
+}
+
+// a read-only window underneath the focus, for text too long for the message line.
+// one that's already open is reused, so they don't pile up
+async fn open_page(root: &ARW<Root>, zipper: &DynZipper, title: &str, content: String) -> Result<DynZipper> {
+    let mut page = Text::raw(content);
+    page.read_only = true;
+    page.title = Some(title.to_string());
+    // pages aren't worth keeping in the buffer list
+    let mut page = View::new(0, Arc::new(RwLock::new(page)));
+    match windows::find_view(root, |view| view.buffer == 0).await {
+        Some((path, view)) => {
+            let mut view = view.write().await;
+            page.height = view.height;
+            *view = page;
+            drop(view);
+            zipper.highlight(false).await;
+            Ok(windows::enter(root, &path).await)
+        },
+        None => windows::split(root, zipper, SplitDirection::Horizontal, page).await,
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::{bail, Result};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+const UTF16BE_BOM: [u8; 2] = [0xFE, 0xFF];
+
+impl Encoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8-bom",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin-1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        match name.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "utf-8-bom" | "utf8-bom" => Some(Encoding::Utf8Bom),
+            "utf-16le" | "utf16le" => Some(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Encoding::Utf16Be),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    // byte order marks win, then strict utf-8, and anything else is read as latin-1
+    // since every byte sequence is valid latin-1
+    pub fn decode(bytes: &[u8]) -> (String, Encoding) {
+        if let Some(rest) = bytes.strip_prefix(&UTF8_BOM) {
+            return (String::from_utf8_lossy(rest).into_owned(), Encoding::Utf8Bom)
+        }
+        if let Some(rest) = bytes.strip_prefix(&UTF16LE_BOM) {
+            return (decode_utf16(rest, u16::from_le_bytes), Encoding::Utf16Le)
+        }
+        if let Some(rest) = bytes.strip_prefix(&UTF16BE_BOM) {
+            return (decode_utf16(rest, u16::from_be_bytes), Encoding::Utf16Be)
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(content) => (content.to_string(), Encoding::Utf8),
+            Err(_) => (bytes.iter().map(|&b| b as char).collect(), Encoding::Latin1),
+        }
+    }
+
+    pub fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8Bom => &UTF8_BOM,
+            Encoding::Utf16Le => &UTF16LE_BOM,
+            Encoding::Utf16Be => &UTF16BE_BOM,
+            Encoding::Utf8 | Encoding::Latin1 => &[],
+        }
+    }
+
+    // what the byte order mark at the start of a file says, utf-8 without one
+    pub fn sniff(head: &[u8]) -> Encoding {
+        if head.starts_with(&UTF8_BOM) { return Encoding::Utf8Bom }
+        if head.starts_with(&UTF16LE_BOM) { return Encoding::Utf16Le }
+        if head.starts_with(&UTF16BE_BOM) { return Encoding::Utf16Be }
+        Encoding::Utf8
+    }
+
+    // encodes without the byte order mark, so a document can be written out chunk by chunk
+    pub fn encode(&self, content: &str) -> Result<Vec<u8>> {
+        Ok(match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => content.as_bytes().to_vec(),
+            Encoding::Utf16Le => content.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Encoding::Utf16Be => content.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            Encoding::Latin1 => {
+                let mut bytes = Vec::with_capacity(content.len());
+                for ch in content.chars() {
+                    match u8::try_from(ch) {
+                        Ok(b) => bytes.push(b),
+                        Err(_) => bail!("{:?} can't be represented in {}", ch, self.name()),
+                    }
+                }
+                bytes
+            },
+        })
+    }
+}
+
+// decodes a file a chunk at a time, holding on to characters split between chunks
+pub struct Decoder {
+    encoding: Encoding,
+    carried: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new(encoding: Encoding) -> Decoder {
+        Decoder { encoding, carried: Vec::new() }
+    }
+
+    // the whole characters so far. `last` says there's nothing after `bytes`.
+    // none when the bytes aren't utf-8 after all, which is only known as they come
+    pub fn decode(&mut self, bytes: &[u8], last: bool) -> Option<String> {
+        self.carried.extend_from_slice(bytes);
+        let whole = match self.encoding {
+            Encoding::Latin1 => self.carried.len(),
+            Encoding::Utf8 | Encoding::Utf8Bom => match std::str::from_utf8(&self.carried) {
+                Ok(_) => self.carried.len(),
+                // cut off at the end of the chunk
+                Err(e) if e.error_len().is_none() && !last => e.valid_up_to(),
+                Err(_) if self.encoding == Encoding::Utf8 => return None,
+                Err(_) => self.carried.len(),
+            },
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let whole = self.carried.len() & !1;
+                let from_bytes = if self.encoding == Encoding::Utf16Le { u16::from_le_bytes } else { u16::from_be_bytes };
+                // the first half of a surrogate pair waits for the second
+                let high = whole >= 2 && (0xD800..0xDC00).contains(&from_bytes([self.carried[whole - 2], self.carried[whole - 1]]));
+                if high && !last { whole - 2 } else { whole }
+            },
+        };
+        let rest = self.carried.split_off(whole);
+        let bytes = std::mem::replace(&mut self.carried, rest);
+        Some(match self.encoding {
+            Encoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+            Encoding::Utf8 | Encoding::Utf8Bom => String::from_utf8_lossy(&bytes).into_owned(),
+            Encoding::Utf16Le => decode_utf16(&bytes, u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(&bytes, u16::from_be_bytes),
+        })
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // feeds `bytes` through a decoder in chunks of `size`
+    fn chunked(encoding: Encoding, bytes: &[u8], size: usize) -> Option<String> {
+        let mut decoder = Decoder::new(encoding);
+        let mut out = String::new();
+        for chunk in bytes.chunks(size) {
+            out.push_str(&decoder.decode(chunk, false)?);
+        }
+        out.push_str(&decoder.decode(&[], true)?);
+        Some(out)
+    }
+
+    #[test]
+    fn characters_split_between_chunks() {
+        let text = "héllo wörld 🦀\n";
+        for size in 1..8 {
+            assert_eq!(chunked(Encoding::Utf8, text.as_bytes(), size).as_deref(), Some(text));
+            let le = Encoding::Utf16Le.encode(text).unwrap();
+            assert_eq!(chunked(Encoding::Utf16Le, &le, size).as_deref(), Some(text));
+            let be = Encoding::Utf16Be.encode(text).unwrap();
+            assert_eq!(chunked(Encoding::Utf16Be, &be, size).as_deref(), Some(text));
+        }
+    }
+
+    #[test]
+    fn not_utf8() {
+        let bytes = b"caf\xe9\n";
+        assert_eq!(chunked(Encoding::Utf8, bytes, 2), None);
+        assert_eq!(chunked(Encoding::Latin1, bytes, 2).as_deref(), Some("café\n"));
+        // cut off at the very end
+        assert_eq!(chunked(Encoding::Utf8, b"ab\xc3", 2), None);
+        assert_eq!(chunked(Encoding::Utf8Bom, b"ab\xc3", 2).as_deref(), Some("ab\u{fffd}"));
+    }
+
+    #[test]
+    fn sniff() {
+        assert_eq!(Encoding::sniff(b"\xef\xbb\xbfabc"), Encoding::Utf8Bom);
+        assert_eq!(Encoding::sniff(b"\xff\xfea\0"), Encoding::Utf16Le);
+        assert_eq!(Encoding::sniff(b"\xfe\xff\0a"), Encoding::Utf16Be);
+        assert_eq!(Encoding::sniff(b"ab"), Encoding::Utf8);
+        assert_eq!(Encoding::sniff(b""), Encoding::Utf8);
+    }
+}
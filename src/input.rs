@@ -1,54 +1,101 @@
 
-use std::time::Duration;
+use std::path::PathBuf;
 
-use crossterm::event::{self, Event, KeyCode, ModifierKeyCode};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEvent, MouseEventKind};
+use futures::StreamExt;
 use ratatui::layout::Rect;
-use tokio::{sync::{mpsc::UnboundedSender, RwLock}, task::JoinHandle, time::Instant};
+use tokio::{sync::{mpsc::UnboundedSender, RwLock}, task::JoinHandle, time::{sleep, Duration}};
 
-use crate::{primatives::Root, State};
+use crate::{
+    cmdline::{CmdLineEdit, LineRange},
+    config::config,
+    keymap::{self, Action, Keymaps},
+    primatives::SplitDirection,
+    tabs::TabIndex,
+    windows::{Direction, Resize},
+    State
+};
 
 pub fn input_thread_init(
     state: &'static RwLock<State>,
-    root: &'static RwLock<Root>,
+    keymaps: &'static RwLock<Keymaps>,
     input_tx: UnboundedSender<Command>,
-    deadline: u64
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
-        let tick_rate = Duration::from_nanos(deadline);
-        let mut last_tick = Instant::now();
-        let mut mod_keys = Vec::new();
+        let mut events = EventStream::new();
+        // keys typed so far towards a binding longer than them
+        let mut pending = Vec::new();
         loop {
+            let timeout = Duration::from_millis(config().key_timeout);
+            let event = tokio::select! {
+                event = events.next() => event,
+                // nothing more came, so they're taken as they are
+                _ = sleep(timeout), if !pending.is_empty() => {
+                    flush(state, keymaps, &mut pending, &input_tx).await;
+                    continue
+                },
+            };
+            let Some(Ok(event)) = event else { break };
             if *state.read().await == State::ShutDown { break }
+            // some terminals report keys being let go of as well, only presses count
+            if let Event::Key(KeyEvent { kind: KeyEventKind::Release, .. }) = event { continue }
 
-            let timeout = tick_rate.saturating_sub(last_tick.elapsed());
-            if crossterm::event::poll(timeout).unwrap() {
-                let event = event::read().unwrap();
-                
-                match event {
-                    Event::FocusLost => (),
-                    Event::FocusGained => (),
-                    Event::Resize(columns, rows) => root.write().await.area = Rect::new(0, 0, columns, rows),
-                    _ => (),
-                }
+            match event {
+                Event::FocusLost => (),
+                Event::FocusGained => (),
+                Event::Resize(columns, rows) => {
+                    input_tx.send(Command::TerminalResized(Rect::new(0, 0, columns, rows))).unwrap();
+                },
+                // moving without a button held down doesn't do anything
+                Event::Mouse(MouseEvent { kind: MouseEventKind::Moved, .. }) => (),
+                Event::Mouse(mouse) => input_tx.send(Command::Mouse(mouse)).unwrap(),
+                // a paste comes as one event, into the command line a character at a time.
+                // keys still waiting on a longer binding were typed before it, so they go first
+                Event::Paste(ref content) => {
+                    if !pending.is_empty() { flush(state, keymaps, &mut pending, &input_tx).await }
+                    match *state.read().await {
+                        State::Command => content
+                            .chars()
+                            .filter(|ch| !ch.is_control())
+                            .for_each(|ch| input_tx.send(Command::CmdLine(CmdLineEdit::Insert(ch))).unwrap()),
+                        _ => input_tx.send(Command::Paste(content.clone())).unwrap(),
+                    }
+                },
+                _ => (),
+            }
 
-                let msg = match *state.read().await {
-                    State::Normal => handle_normal(&mut mod_keys, event).await,
-                    State::Insert => handle_insert(&mut mod_keys, event).await,
-                    State::Travel => handle_travel(&mut mod_keys, event).await,
-                    State::ShutDown => break,
-                };
-                if let Some(msg) = msg {
-                    input_tx.send(msg).unwrap();
-                }
-                last_tick = Instant::now();
+            let waiting = !pending.is_empty();
+            let msgs = match (*state.read().await, event) {
+                (State::Command, event) => handle_command(event).await.into_iter().collect(),
+                (State::ShutDown, _) => break,
+                (state, Event::Key(key)) => keymaps.read().await.feed(state, &mut pending, key),
+                _ => Vec::new(),
+            };
+            if waiting || !pending.is_empty() {
+                input_tx.send(Command::PendingKeys(keymap::display(&pending))).unwrap();
             }
-        };
+            msgs.into_iter().for_each(|msg| input_tx.send(msg).unwrap());
+        }
     })
 }
 
+// takes the pending keys as they are, for when nothing more is coming
+async fn flush(
+    state: &'static RwLock<State>,
+    keymaps: &'static RwLock<Keymaps>,
+    pending: &mut Vec<keymap::Key>,
+    input_tx: &UnboundedSender<Command>,
+) {
+    let state = *state.read().await;
+    let msgs = keymaps.read().await.flush(state, pending);
+    input_tx.send(Command::PendingKeys(String::new())).unwrap();
+    msgs.into_iter().for_each(|msg| input_tx.send(msg).unwrap());
+}
+
 #[derive(PartialEq, Eq)]
 pub enum Command {
     Insert(char),
+    Backspace,
     NormalMode,
     InsertMode,
     TravelMode,
@@ -57,6 +104,8 @@ pub enum Command {
     ToLeftSibling,
     ToRightSibling,
     Reset,
+    Quit,
+    WriteQuit,
     ShutDown,
     PrevChar,
     PrevLine,
@@ -64,77 +113,74 @@ pub enum Command {
     NextChar,
     ToLastChild,
     ToMiddleChild,
+    Write { force: bool }, // force writes read-only texts too
+    Update, // writes only when there are changes
+    ExMode,
+    CmdLine(CmdLineEdit),
+    GotoLine(LineRange),
+    DeleteLines(LineRange),
+    WriteAs(PathBuf),
+    WriteAll,
+    Edit { path: PathBuf, force: bool },
+    Split(SplitDirection, Option<PathBuf>),
+    Set(String, String),
+    Messages { clear: bool },
+    CloseWindow { force: bool },
+    OnlyWindow { force: bool },
+    FocusWindow(Direction),
+    ResizeWindow(Resize),
+    Equalize,
+    TerminalResized(Rect),
+    TabNew(Option<PathBuf>),
+    TabClose { force: bool },
+    SwitchTab(TabIndex),
+    MoveTab(TabIndex),
+    ListBuffers,
+    SwitchBuffer(String), // a number or part of a name
+    CycleBuffer(isize),
+    DeleteBuffer { buffer: Option<String>, force: bool },
+    Ex(String), // a command line run from a key
+    ListKeys(Option<State>),
+    MapKey(State, String, Option<Action>), // no action unbinds the keys
+    PendingKeys(String), // typed so far towards a longer binding
+    Mouse(MouseEvent),
+    Paste(String),
+    Undo,
+    Redo,
+    DeleteSelection,
+    ColorScheme(Option<String>), // shows the current theme without a name
 }
 
-pub async fn handle_normal(
-    _mod_keys: &mut Vec<ModifierKeyCode>,
-    input: Event
-) -> Option<Command> {
-    // let shift = mod_keys.read().await.iter().find(|&k| *k == ModifierKeyCode::LeftShift || *k == ModifierKeyCode::RightShift).is_some();
-    // let ctrl = mod_keys.read().await.iter()
-    //     .find(|&k| *k == ModifierKeyCode::LeftControl || *k == ModifierKeyCode::RightControl)
-    //     .is_some();
-    // let alt = mod_keys.read().await.iter().find(|&k| *k == ModifierKeyCode::LeftAlt || *k == ModifierKeyCode::RightAlt).is_some();
-    // let meta = mod_keys.read().await.iter().find(|&k| *k == ModifierKeyCode::LeftMeta || *k == ModifierKeyCode::RightMeta).is_some();
-    // let super_ = mod_keys.read().await.iter().find(|&k| *k == ModifierKeyCode::LeftSuper || *k == ModifierKeyCode::RightSuper).is_some();
-    // let hyper = mod_keys.read().await.iter().find(|&k| *k == ModifierKeyCode::LeftHyper || *k == ModifierKeyCode::RightHyper).is_some();
-    match input {
-        Event::Key(key) => match key.code {
-            KeyCode::Esc => Some(Command::ShutDown),
-            KeyCode::Char('i') => Some(Command::InsertMode),
-            KeyCode::Char('t') => Some(Command::TravelMode),
-            KeyCode::Char('h') => Some(Command::PrevChar),
-            KeyCode::Char('j') => Some(Command::PrevLine),
-            KeyCode::Char('k') => Some(Command::NextLine),
-            KeyCode::Char('l') => Some(Command::NextChar),
-            _ => None,
-        },
-        _ => None,
-    }
-}
-
-pub async fn handle_insert(
-    mod_keys: &mut Vec<ModifierKeyCode>,
-    input: Event
-) -> Option<Command> {
-    let ctrl = mod_keys.iter().find(|&k| *k == ModifierKeyCode::LeftControl || *k == ModifierKeyCode::RightControl).is_some();
-    match input {
-        Event::Key(key) => match key.code {
-            KeyCode::Esc => Some(Command::NormalMode),
-
-            KeyCode::Char('t') if ctrl => Some(Command::TravelMode),
-            KeyCode::Char('h') if ctrl => Some(Command::PrevChar),
-            KeyCode::Char('j') if ctrl => Some(Command::PrevLine),
-            KeyCode::Char('k') if ctrl => Some(Command::NextLine),
-            KeyCode::Char('l') if ctrl => Some(Command::NextChar),
-
-            KeyCode::Char(ch) => Some(Command::Insert(ch)),
-            _ => None,
+pub async fn handle_command(input: Event) -> Option<Command> {
+    let edit = match input {
+        Event::Key(key) if key.modifiers.contains(KeyModifiers::CONTROL) => match key.code {
+            KeyCode::Char('[') => CmdLineEdit::Cancel,
+            KeyCode::Char('m') => CmdLineEdit::Submit,
+            KeyCode::Char('i') => CmdLineEdit::Complete,
+            KeyCode::Char('h') => CmdLineEdit::Backspace,
+            KeyCode::Char('b') => CmdLineEdit::Home,
+            KeyCode::Char('e') => CmdLineEdit::End,
+            KeyCode::Char('p') => CmdLineEdit::HistoryPrev,
+            KeyCode::Char('n') => CmdLineEdit::HistoryNext,
+            _ => return None,
         },
-        _ => None,
-    }
-}
-
-pub async fn handle_travel(
-    mod_keys: &mut Vec<ModifierKeyCode>,
-    input: Event
-) -> Option<Command> {
-    let ctrl = mod_keys.iter().find(|&k| *k == ModifierKeyCode::LeftControl || *k == ModifierKeyCode::RightControl).is_some();
-    match input {
         Event::Key(key) => match key.code {
-            KeyCode::Esc => Some(Command::NormalMode),
-
-            KeyCode::Char('i') if ctrl => Some(Command::ToFirstChild),
-            KeyCode::Char('k') => Some(Command::ToParent),
-            KeyCode::Char('h') => Some(Command::ToLeftSibling),
-            KeyCode::Char('l') => Some(Command::ToRightSibling),
-            KeyCode::Char('j') => Some(Command::ToFirstChild),
-            KeyCode::Char('a') => Some(Command::ToLastChild),
-            KeyCode::Char('m') => Some(Command::ToMiddleChild),
-
-            _ => None,
+            KeyCode::Esc => CmdLineEdit::Cancel,
+            KeyCode::Enter => CmdLineEdit::Submit,
+            KeyCode::Tab => CmdLineEdit::Complete,
+            KeyCode::Backspace => CmdLineEdit::Backspace,
+            KeyCode::Delete => CmdLineEdit::Delete,
+            KeyCode::Left => CmdLineEdit::Left,
+            KeyCode::Right => CmdLineEdit::Right,
+            KeyCode::Home => CmdLineEdit::Home,
+            KeyCode::End => CmdLineEdit::End,
+            KeyCode::Up => CmdLineEdit::HistoryPrev,
+            KeyCode::Down => CmdLineEdit::HistoryNext,
+            KeyCode::Char(_) if key.modifiers.intersects(!KeyModifiers::SHIFT) => return None,
+            KeyCode::Char(ch) => CmdLineEdit::Insert(ch),
+            _ => return None,
         },
-        _ => None,
-    }
+        _ => return None,
+    };
+    Some(Command::CmdLine(edit))
 }
-
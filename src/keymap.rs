@@ -0,0 +1,587 @@
+use std::{collections::HashMap, fmt};
+
+use anyhow::{bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{
+    config::Config,
+    input::Command,
+    primatives::SplitDirection,
+    tabs::TabIndex,
+    tui,
+    windows::{Direction, Resize},
+    State
+};
+
+//
+// a table per mode from runs of keys to what they do. keys are written like
+// vim's, `gt`, `<Esc>`, `<C-w>h`, and do either one of the named actions below
+// or run a command line, written with its leading colon
+//
+
+type Builtin = fn() -> Command;
+
+// (name, command), what a key can be bound to besides a command line
+const ACTIONS: &[(&str, Builtin)] = &[
+    ("backspace", || Command::Backspace),
+    ("close", || Command::CloseWindow { force: false }),
+    ("delete-selection", || Command::DeleteSelection),
+    ("equalize", || Command::Equalize),
+    ("ex-mode", || Command::ExMode),
+    ("first-child", || Command::ToFirstChild),
+    ("focus-down", || Command::FocusWindow(Direction::Down)),
+    ("focus-left", || Command::FocusWindow(Direction::Left)),
+    ("focus-right", || Command::FocusWindow(Direction::Right)),
+    ("focus-up", || Command::FocusWindow(Direction::Up)),
+    ("grow", || Command::ResizeWindow(Resize::By(1))),
+    ("insert-mode", || Command::InsertMode),
+    ("last-child", || Command::ToLastChild),
+    ("left-sibling", || Command::ToLeftSibling),
+    ("middle-child", || Command::ToMiddleChild),
+    ("newline", || Command::Insert('\n')),
+    ("next-char", || Command::NextChar),
+    ("next-line", || Command::NextLine),
+    ("next-tab", || Command::SwitchTab(TabIndex::Relative(1))),
+    ("normal-mode", || Command::NormalMode),
+    ("only", || Command::OnlyWindow { force: false }),
+    ("parent", || Command::ToParent),
+    ("prev-char", || Command::PrevChar),
+    ("prev-line", || Command::PrevLine),
+    ("prev-tab", || Command::SwitchTab(TabIndex::Relative(-1))),
+    ("quit", || Command::Quit),
+    ("redo", || Command::Redo),
+    ("right-sibling", || Command::ToRightSibling),
+    ("shrink", || Command::ResizeWindow(Resize::By(-1))),
+    ("shut-down", || Command::ShutDown),
+    ("split", || Command::Split(SplitDirection::Horizontal, None)),
+    ("travel-mode", || Command::TravelMode),
+    ("undo", || Command::Undo),
+    ("vsplit", || Command::Split(SplitDirection::Vertical, None)),
+    ("write", || Command::Write { force: false }),
+    ("write-quit", || Command::WriteQuit),
+];
+
+// binding to this takes the key out of the table
+pub const UNBIND: &str = "nop";
+
+const DEFAULTS: &[(State, &str, &str)] = &[
+    (State::Normal, "<Esc>", "quit"),
+    (State::Normal, "Q", "shut-down"),
+    (State::Normal, "W", "write-quit"),
+    (State::Normal, "i", "insert-mode"),
+    (State::Normal, "t", "travel-mode"),
+    (State::Normal, "w", "write"),
+    (State::Normal, "h", "prev-char"),
+    (State::Normal, "j", "prev-line"),
+    (State::Normal, "k", "next-line"),
+    (State::Normal, "l", "next-char"),
+    (State::Normal, ":", "ex-mode"),
+    (State::Normal, "s", "split"),
+    (State::Normal, "v", "vsplit"),
+    (State::Normal, "c", "close"),
+    (State::Normal, "o", "only"),
+    (State::Normal, "H", "focus-left"),
+    (State::Normal, "J", "focus-down"),
+    (State::Normal, "K", "focus-up"),
+    (State::Normal, "L", "focus-right"),
+    (State::Normal, "+", "grow"),
+    (State::Normal, "-", "shrink"),
+    (State::Normal, "=", "equalize"),
+    (State::Normal, ">", "next-tab"),
+    (State::Normal, "<lt>", "prev-tab"),
+    (State::Normal, "u", "undo"),
+    (State::Normal, "<C-r>", "redo"),
+    (State::Normal, "d", "delete-selection"),
+    (State::Normal, "gt", "next-tab"),
+    (State::Normal, "gT", "prev-tab"),
+    (State::Normal, "<C-w>h", "focus-left"),
+    (State::Normal, "<C-w>j", "focus-down"),
+    (State::Normal, "<C-w>k", "focus-up"),
+    (State::Normal, "<C-w>l", "focus-right"),
+    (State::Normal, "<C-w>s", "split"),
+    (State::Normal, "<C-w>v", "vsplit"),
+    (State::Normal, "<C-w>c", "close"),
+    (State::Normal, "<C-w>o", "only"),
+    (State::Normal, "<C-w>=", "equalize"),
+    (State::Insert, "<Esc>", "normal-mode"),
+    (State::Insert, "<C-t>", "travel-mode"),
+    (State::Insert, "<C-h>", "prev-char"),
+    (State::Insert, "<C-j>", "prev-line"),
+    (State::Insert, "<C-k>", "next-line"),
+    (State::Insert, "<C-l>", "next-char"),
+    (State::Insert, "<Enter>", "newline"),
+    (State::Insert, "<BS>", "backspace"),
+    (State::Travel, "<Esc>", "normal-mode"),
+    (State::Travel, "<C-i>", "first-child"),
+    (State::Travel, "k", "parent"),
+    (State::Travel, "h", "left-sibling"),
+    (State::Travel, "l", "right-sibling"),
+    (State::Travel, "j", "first-child"),
+    (State::Travel, "a", "last-child"),
+    (State::Travel, "m", "middle-child"),
+];
+
+pub const MODES: &[(State, &str)] = &[
+    (State::Normal, "normal"),
+    (State::Insert, "insert"),
+    (State::Travel, "travel"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for Key {
+    // shift is already in the case of a character, and Shift+Tab is sent as a key of its own
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers;
+        let code = match event.code {
+            KeyCode::BackTab => { modifiers |= KeyModifiers::SHIFT; KeyCode::Tab },
+            KeyCode::Char(ch) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(ch.to_ascii_uppercase())
+            },
+            code => code,
+        };
+        Key { code, modifiers }
+    }
+}
+
+// (written as, modifier), in the order they're written out
+const MODIFIERS: &[(&str, KeyModifiers)] = &[
+    ("C", KeyModifiers::CONTROL),
+    ("A", KeyModifiers::ALT),
+    ("M", KeyModifiers::ALT),
+    ("T", KeyModifiers::META),
+    ("D", KeyModifiers::SUPER),
+    ("H", KeyModifiers::HYPER),
+    ("S", KeyModifiers::SHIFT),
+];
+
+const NAMES: &[(&str, KeyCode)] = &[
+    ("Esc", KeyCode::Esc),
+    ("Enter", KeyCode::Enter),
+    ("CR", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("BS", KeyCode::Backspace),
+    ("Del", KeyCode::Delete),
+    ("Space", KeyCode::Char(' ')),
+    ("lt", KeyCode::Char('<')),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Insert", KeyCode::Insert),
+];
+
+impl Key {
+    pub fn parse(notation: &str) -> Result<Key> {
+        let mut chars = notation.chars();
+        if let (Some(ch), None) = (chars.next(), chars.next()) {
+            return Ok(Key { code: KeyCode::Char(ch), modifiers: KeyModifiers::NONE })
+        }
+        let Some(inner) = notation.strip_prefix('<').and_then(|n| n.strip_suffix('>')) else {
+            bail!("not a key: {}", notation)
+        };
+
+        // modifiers come first, `<C-A-x>`, and a lone `-` is a key of its own
+        let mut modifiers = KeyModifiers::NONE;
+        let mut name = inner;
+        while let Some((modifier, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+            match MODIFIERS.iter().find(|(written, _)| written.eq_ignore_ascii_case(modifier)) {
+                Some((_, flag)) => modifiers |= *flag,
+                None => bail!("not a key: {}", notation),
+            }
+            name = rest;
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(ch), None) => KeyCode::Char(ch),
+            _ => match NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+                Some((_, code)) => *code,
+                None => match name.strip_prefix(['F', 'f']).and_then(|n| n.parse().ok()) {
+                    Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => bail!("not a key: {}", notation),
+                },
+            },
+        };
+        if let KeyCode::Char(_) = code { modifiers.remove(KeyModifiers::SHIFT) }
+        Ok(Key { code, modifiers })
+    }
+}
+
+impl Key {
+    // the control key sent as the same byte, when there is one
+    fn legacy(&self) -> Option<Key> {
+        let ch = match self.code {
+            KeyCode::Tab => 'i',
+            KeyCode::Enter => 'm',
+            KeyCode::Esc => '[',
+            _ => return None,
+        };
+        if !self.modifiers.is_empty() { return None }
+        Some(Key { code: KeyCode::Char(ch), modifiers: KeyModifiers::CONTROL })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.code {
+            KeyCode::Char('<') => "lt".to_string(),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(ch) => ch.to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            code => match NAMES.iter().find(|(_, c)| *c == code) {
+                Some((name, _)) => name.to_string(),
+                None => format!("{:?}", code),
+            },
+        };
+        let mut prefix = String::new();
+        for (written, flag) in MODIFIERS.iter().filter(|(written, _)| *written != "M") {
+            if self.modifiers.contains(*flag) { prefix.push_str(written); prefix.push('-') }
+        }
+
+        // plain characters are written as they are
+        if prefix.is_empty() && name.chars().count() == 1 { return write!(f, "{}", name) }
+        write!(f, "<{}{}>", prefix, name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Builtin(&'static str),
+    Ex(String), // a command line, without its colon
+}
+
+impl Action {
+    pub fn parse(action: &str) -> Result<Action> {
+        if let Some(line) = action.strip_prefix(':') {
+            return Ok(Action::Ex(line.to_string()))
+        }
+        match ACTIONS.iter().find(|(name, _)| *name == action) {
+            Some((name, _)) => Ok(Action::Builtin(name)),
+            None => bail!("not an action: {}", action),
+        }
+    }
+
+    fn command(&self) -> Command {
+        match self {
+            Action::Builtin(name) => (ACTIONS.iter().find(|(n, _)| n == name).unwrap().1)(),
+            Action::Ex(line) => Command::Ex(line.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Builtin(name) => write!(f, "{}", name),
+            Action::Ex(line) => write!(f, ":{}", line),
+        }
+    }
+}
+
+pub fn mode(name: &str) -> Result<State> {
+    match MODES.iter().find(|(_, n)| *n == name) {
+        Some((state, _)) => Ok(*state),
+        None => bail!("not a mode: {}", name),
+    }
+}
+
+#[derive(Clone)]
+pub struct Keymaps {
+    maps: HashMap<State, HashMap<Vec<Key>, Action>>,
+    leader: Key, // what `<leader>` stands for
+}
+
+impl Keymaps {
+    pub fn new(leader: Key) -> Keymaps {
+        let mut keymaps = Keymaps { maps: HashMap::new(), leader };
+        for (state, keys, action) in DEFAULTS {
+            keymaps.set(*state, keys, action).unwrap();
+        }
+        keymaps
+    }
+
+    // the defaults, with the config's bindings put over them. they're keyed by
+    // mode name, then key notation, with the action as the value
+    pub fn with(config: &Config) -> Result<Keymaps> {
+        let mut keymaps = Keymaps::new(Key::parse(&config.leader)?);
+        for (mode_name, bindings) in config.keys.iter() {
+            let state = mode(mode_name)?;
+            for (keys, action) in bindings {
+                keymaps.set(state, keys, action)?;
+            }
+        }
+        Ok(keymaps)
+    }
+
+    // binds or, with `nop`, unbinds keys written out in the config's notation
+    pub fn set(&mut self, state: State, keys: &str, action: &str) -> Result<()> {
+        let action = if action == UNBIND { None } else { Some(Action::parse(action)?) };
+        self.map(state, keys, action)
+    }
+
+    pub fn map(&mut self, state: State, keys: &str, action: Option<Action>) -> Result<()> {
+        let keys = self.parse(keys)?;
+        let map = self.maps.entry(state).or_default();
+        match action {
+            Some(action) => { map.insert(keys, action); },
+            None => { map.remove(&keys); },
+        }
+        Ok(())
+    }
+
+    // a run of keys like `gt`, `<C-w>h` or `<leader>w`. a `<` that doesn't
+    // start a key name is just the character
+    pub fn parse(&self, notation: &str) -> Result<Vec<Key>> {
+        let mut keys = Vec::new();
+        let mut rest = notation;
+        while let Some(ch) = rest.chars().next() {
+            if let Some(end) = rest.find('>').filter(|_| ch == '<') {
+                let name = &rest[..=end];
+                let key = match name.eq_ignore_ascii_case("<leader>") {
+                    true => Some(self.leader),
+                    false => Key::parse(name).ok(),
+                };
+                if let Some(key) = key {
+                    keys.push(key);
+                    rest = &rest[end + 1..];
+                    continue
+                }
+            }
+            keys.push(Key { code: KeyCode::Char(ch), modifiers: KeyModifiers::NONE });
+            rest = &rest[ch.len_utf8()..];
+        }
+        if keys.is_empty() { bail!("no keys given") }
+        Ok(keys)
+    }
+
+    // whether `keys` are bound, and whether they start a longer binding
+    fn find(&self, state: State, keys: &[Key]) -> (bool, bool) {
+        let Some(map) = self.maps.get(&state) else { return (false, false) };
+        let longer = map.keys().any(|bound| bound.len() > keys.len() && bound.starts_with(keys));
+        (map.contains_key(keys), longer)
+    }
+
+    // adds a key to the ones waiting in `pending`, giving back whatever they
+    // settle into. they wait while they could still become a longer binding
+    pub fn feed(&self, state: State, pending: &mut Vec<Key>, event: KeyEvent) -> Vec<Command> {
+        let key = Key::from(event);
+        if key.code == KeyCode::Esc && !pending.is_empty() {
+            pending.clear();
+            return Vec::new()
+        }
+        pending.push(key);
+        let (mut bound, mut longer) = self.find(state, pending);
+        // without the keyboard enhancement, terminals send Ctrl+i as Tab and the
+        // like, so those keys fall back to the bindings of what they could be
+        if let Some(legacy) = key.legacy().filter(|_| !bound && !longer && !tui::enhanced()) {
+            *pending.last_mut().unwrap() = legacy;
+            (bound, longer) = self.find(state, pending);
+            if !bound && !longer { *pending.last_mut().unwrap() = key }
+        }
+        if longer { return Vec::new() }
+        if bound || pending.len() == 1 { return self.flush(state, pending) }
+
+        // the key doesn't carry on from the ones before it, so they're settled first
+        pending.pop();
+        let mut commands = self.flush(state, pending);
+        commands.extend(self.feed(state, pending, event));
+        commands
+    }
+
+    // settles the pending keys when nothing more is coming, running the
+    // longest bindings they start with. characters without a binding of their
+    // own are typed in insert mode
+    pub fn flush(&self, state: State, pending: &mut Vec<Key>) -> Vec<Command> {
+        let mut commands = Vec::new();
+        let map = self.maps.get(&state);
+        while !pending.is_empty() {
+            let longest = (1..=pending.len())
+                .rev()
+                .find_map(|len| map.and_then(|map| map.get(&pending[..len])).map(|action| (len, action)));
+            let len = match longest {
+                Some((len, action)) => { commands.push(action.command()); len },
+                None => {
+                    let key = pending[0];
+                    match key.code {
+                        KeyCode::Char(ch) if state == State::Insert && key.modifiers.is_empty() => {
+                            commands.push(Command::Insert(ch))
+                        },
+                        _ => (),
+                    }
+                    1
+                },
+            };
+            pending.drain(..len);
+        }
+        commands
+    }
+
+    // `mode keys action` lines, sorted, for one mode or all of them
+    pub fn list(&self, only: Option<State>) -> String {
+        let mut out = String::new();
+        for (state, name) in MODES {
+            if only.is_some_and(|only| only != *state) { continue }
+            let Some(map) = self.maps.get(state) else { continue };
+            let mut lines: Vec<(String, String)> = map
+                .iter()
+                .map(|(keys, action)| (display(keys), action.to_string()))
+                .collect();
+            lines.sort();
+            for (keys, action) in lines {
+                out.push_str(&format!("{:<8}{:<10}{}\n", name, keys, action));
+            }
+        }
+        out
+    }
+}
+
+pub fn display(keys: &[Key]) -> String {
+    keys.iter().map(|key| key.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key { code, modifiers }
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(Key::parse("a").unwrap(), key(KeyCode::Char('a'), KeyModifiers::NONE));
+        assert_eq!(Key::parse("<lt>").unwrap(), key(KeyCode::Char('<'), KeyModifiers::NONE));
+        assert_eq!(Key::parse("<C-->").unwrap(), key(KeyCode::Char('-'), KeyModifiers::CONTROL));
+        assert_eq!(Key::parse("<C-A-x>").unwrap(), key(KeyCode::Char('x'), KeyModifiers::CONTROL | KeyModifiers::ALT));
+        assert_eq!(Key::parse("<m-x>").unwrap(), key(KeyCode::Char('x'), KeyModifiers::ALT));
+        assert_eq!(Key::parse("<F12>").unwrap(), key(KeyCode::F(12), KeyModifiers::NONE));
+        assert_eq!(Key::parse("<S-Tab>").unwrap(), key(KeyCode::Tab, KeyModifiers::SHIFT));
+        assert_eq!(Key::parse("<cr>").unwrap(), key(KeyCode::Enter, KeyModifiers::NONE));
+        // shift is already in a character's case
+        assert_eq!(Key::parse("<S-x>").unwrap(), key(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert!(Key::parse("<F13>").is_err());
+        assert!(Key::parse("<Q-x>").is_err());
+        assert!(Key::parse("<Bogus>").is_err());
+        assert!(Key::parse("ab").is_err());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for notation in ["a", "<lt>", "<C-->", "<C-A-x>", "<F12>", "<Esc>", "<C-w>", "<D-x>", "<S-Tab>", "<Space>", "<C-S-Up>"] {
+            assert_eq!(Key::parse(notation).unwrap().to_string(), notation);
+        }
+        // aliases come back written the one way
+        assert_eq!(Key::parse("<M-x>").unwrap().to_string(), "<A-x>");
+        assert_eq!(Key::parse("<c-W>").unwrap().to_string(), "<C-W>");
+        assert_eq!(Key::parse("<CR>").unwrap().to_string(), "<Enter>");
+    }
+
+    #[test]
+    fn key_events() {
+        let shifted = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::SHIFT);
+        assert_eq!(Key::from(shifted), key(KeyCode::Char('A'), KeyModifiers::NONE));
+        let back_tab = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(Key::from(back_tab), key(KeyCode::Tab, KeyModifiers::SHIFT));
+    }
+
+    #[test]
+    fn sequences() {
+        let keymaps = Keymaps::new(Key::parse("\\").unwrap());
+        let c_w = key(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        let h = key(KeyCode::Char('h'), KeyModifiers::NONE);
+        assert_eq!(keymaps.parse("<C-w>h").unwrap(), vec![c_w, h]);
+        // a `<` that isn't a key name is just the character
+        assert_eq!(keymaps.parse("<h").unwrap().len(), 2);
+        assert_eq!(keymaps.parse("<bogus>").unwrap().len(), 7);
+        assert!(keymaps.parse("").is_err());
+        assert_eq!(display(&[c_w, h]), "<C-w>h");
+    }
+
+    fn press(keymaps: &Keymaps, state: State, pending: &mut Vec<Key>, keys: &str) -> Vec<Command> {
+        let mut commands = Vec::new();
+        for key in keymaps.parse(keys).unwrap() {
+            commands.extend(keymaps.feed(state, pending, KeyEvent::new(key.code, key.modifiers)));
+        }
+        commands
+    }
+
+    #[test]
+    fn prefix_waits() {
+        let mut keymaps = Keymaps::new(Key::parse("\\").unwrap());
+        let mut pending = Vec::new();
+        assert!(press(&keymaps, State::Normal, &mut pending, "gt") == vec![Command::SwitchTab(TabIndex::Relative(1))]);
+        assert!(pending.is_empty());
+
+        // `g` on its own waits for more, then runs when nothing else comes
+        keymaps.map(State::Normal, "g", Some(Action::Ex("bnext".to_string()))).unwrap();
+        assert!(press(&keymaps, State::Normal, &mut pending, "g").is_empty());
+        assert_eq!(display(&pending), "g");
+        assert!(keymaps.flush(State::Normal, &mut pending) == vec![Command::Ex("bnext".to_string())]);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn prefix_broken_off() {
+        let mut keymaps = Keymaps::new(Key::parse("\\").unwrap());
+        keymaps.map(State::Normal, "g", Some(Action::Ex("bnext".to_string()))).unwrap();
+        let mut pending = Vec::new();
+        // `i` doesn't carry on from `g`, so `g` runs and `i` is fed again on its own
+        assert!(press(&keymaps, State::Normal, &mut pending, "gi") == vec![Command::Ex("bnext".to_string()), Command::InsertMode]);
+        assert!(pending.is_empty());
+        // and an unbound prefix is dropped
+        keymaps.map(State::Normal, "g", None).unwrap();
+        assert!(press(&keymaps, State::Normal, &mut pending, "gi") == vec![Command::InsertMode]);
+        // the key fed again can start a sequence of its own
+        assert!(press(&keymaps, State::Normal, &mut pending, "<C-w><C-w>h") == vec![Command::FocusWindow(Direction::Left)]);
+    }
+
+    #[test]
+    fn escape_cancels() {
+        let keymaps = Keymaps::new(Key::parse("\\").unwrap());
+        let mut pending = Vec::new();
+        assert!(press(&keymaps, State::Normal, &mut pending, "<C-w><Esc>").is_empty());
+        assert!(pending.is_empty());
+        // with nothing pending it's a key like any other
+        assert!(press(&keymaps, State::Normal, &mut pending, "<Esc>") == vec![Command::Quit]);
+    }
+
+    #[test]
+    fn insert_typing() {
+        let mut keymaps = Keymaps::new(Key::parse("\\").unwrap());
+        keymaps.map(State::Insert, "jk", Some(Action::Builtin("normal-mode"))).unwrap();
+        let mut pending = Vec::new();
+        assert!(press(&keymaps, State::Insert, &mut pending, "x") == vec![Command::Insert('x')]);
+        assert!(press(&keymaps, State::Insert, &mut pending, "jk") == vec![Command::NormalMode]);
+        assert!(press(&keymaps, State::Insert, &mut pending, "jx") == vec![Command::Insert('j'), Command::Insert('x')]);
+        assert!(press(&keymaps, State::Insert, &mut pending, "j").is_empty());
+        assert!(keymaps.flush(State::Insert, &mut pending) == vec![Command::Insert('j')]);
+        // but not in other modes, or with modifiers held
+        assert!(press(&keymaps, State::Normal, &mut pending, "x").is_empty());
+        assert!(press(&keymaps, State::Insert, &mut pending, "<C-x>").is_empty());
+    }
+
+    #[test]
+    fn leader() {
+        let mut keymaps = Keymaps::new(Key::parse("<Space>").unwrap());
+        let space = key(KeyCode::Char(' '), KeyModifiers::NONE);
+        let w = key(KeyCode::Char('w'), KeyModifiers::NONE);
+        assert_eq!(keymaps.parse("<leader>w").unwrap(), vec![space, w]);
+        assert_eq!(keymaps.parse("<Leader>w").unwrap(), vec![space, w]);
+
+        keymaps.map(State::Normal, "<leader>w", Some(Action::Builtin("write"))).unwrap();
+        let mut pending = Vec::new();
+        assert!(press(&keymaps, State::Normal, &mut pending, "<Space>").is_empty());
+        assert!(press(&keymaps, State::Normal, &mut pending, "w") == vec![Command::Write { force: false }]);
+        assert!(keymaps.list(Some(State::Normal)).contains("<Space>w"));
+    }
+}
@@ -1,17 +1,22 @@
 #![allow(dead_code)]
-use std::path::PathBuf;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
 use std::fmt::Debug;
 use std::sync::Arc;
-use std::fs;
 
 use control::control_thread_init;
 use input::input_thread_init;
-use primatives::Root;
+use pager::stdin_thread_init;
+use primatives::{Root, Text};
 use tokio::sync::mpsc;
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
 use clap::Parser;
+use ratatui::layout::Rect;
 use anyhow::Result;
 use input::Command;
+use config::config;
+use keymap::Keymaps;
+use messages::Level;
 
 mod tui;
 mod primatives;
@@ -19,29 +24,101 @@ mod zipper;
 mod flipflop;
 mod input;
 mod control;
+mod encoding;
+mod pager;
+mod cmdline;
+mod status;
+mod messages;
+mod windows;
+mod tabs;
+mod buffers;
+mod config;
+mod keymap;
+mod mouse;
+mod theme;
 
-use primatives::{WindowRender, SplitDirection, AsyncWidget};
+use primatives::{SplitDirection, AsyncWidget};
+use tabs::{Tabs, TabsRender};
 use tokio::time::{sleep, Instant, Duration};
 
 const BILLIE: u64 = 1_000_000_000;
-const FPS_LIMIT: u64 = 60;
-const RENDER_DEADLINE: u64 = BILLIE / FPS_LIMIT;
-const CONTROL_DEADLINE: u64 = BILLIE / (FPS_LIMIT * 2);
 
 type ARW<T> = Arc<RwLock<T>>;
 
 #[derive(Parser, Debug)]
-struct CLI { path: Option<PathBuf> }
+struct CLI {
+    /// Files to open. `-` reads from stdin, as does piping into the editor
+    paths: Vec<PathBuf>,
+    /// Open the files in horizontal splits, stacked on top of each other
+    #[arg(short = 'o', conflicts_with = "vertical")]
+    horizontal: bool,
+    /// Open the files in vertical splits, side by side
+    #[arg(short = 'O')]
+    vertical: bool,
+    /// Read settings from this file instead of $XDG_CONFIG_HOME/bespoke-ed/config.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Open everything read-only, showing stdin as it arrives
+    #[arg(long)]
+    pager: bool,
+}
+
+const STDIN: &str = "-";
+
+// stdin is read when it's asked for, or when it's piped in and nothing else is.
+// a terminal on stdin is where the keys come from, so that's never read as text
+fn reads_stdin(cli: &CLI) -> bool {
+    cli.paths.iter().any(|p| p == Path::new(STDIN)) || (cli.paths.is_empty() && !io::stdin().is_terminal())
+}
+
+// texts are read before the terminal is taken over so errors print normally.
+// in pager mode stdin is left empty for stdin_thread_init to fill in
+fn open_texts(cli: &CLI) -> Result<Vec<Text>> {
+    let mut paths = cli.paths.clone();
+    if paths.is_empty() && reads_stdin(cli) {
+        paths.push(STDIN.into());
+    }
+    if paths.is_empty() {
+        let mut text = Text::empty();
+        text.read_only = cli.pager;
+        return Ok(vec![text])
+    }
 
-#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+    let mut texts = Vec::with_capacity(paths.len());
+    for path in paths.iter() {
+        let mut text = match (path == Path::new(STDIN), cli.pager) {
+            (true, true) => Text::default(),
+            (true, false) => Text::from_reader(io::stdin().lock())?,
+            (false, _) => Text::open(path)?,
+        };
+        text.read_only = cli.pager;
+        texts.push(text);
+    }
+    Ok(texts)
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
 enum State {
     #[default]
     Normal,
     Insert,
     Travel,
+    Command,
     ShutDown,
 }
 
+impl State {
+    pub fn name(&self) -> &'static str {
+        match self {
+            State::Normal => "NORMAL",
+            State::Insert => "INSERT",
+            State::Travel => "TRAVEL",
+            State::Command => "COMMAND",
+            State::ShutDown => "",
+        }
+    }
+}
+
 pub async fn timeout_sleep(tick_rate: Duration, last_tick: Instant) -> Instant {
     let timeout = tick_rate.saturating_sub(last_tick.elapsed());
     if !timeout.is_zero() { sleep(timeout).await; }
@@ -50,56 +127,115 @@ pub async fn timeout_sleep(tick_rate: Duration, last_tick: Instant) -> Instant {
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = CLI::parse();
+    let (settings, config_errors) = config::load(cli.config.as_deref());
+    config::init(settings);
+    // the config was only kept if its theme loads
+    theme::set(theme::load(&config().theme).unwrap_or_default());
+    let split_dir = match (cli.horizontal, cli.vertical) {
+        (true, _) => SplitDirection::Horizontal,
+        (_, true) => SplitDirection::Vertical,
+        _ => config().split,
+    };
+
+    let texts = match open_texts(&cli) {
+        Ok(texts) => texts,
+        Err(e) => {
+            eprintln!("bespoke-ed: {:#}", e);
+            std::process::exit(1);
+        },
+    };
+    let streamed = cli.pager && reads_stdin(&cli);
+
     tui::install_panic_hook();
 
     let mut terminal = tui::init_app()?;
 
-    let path = CLI::parse().path.expect("File Required");
-    let content = fs::read_to_string(path.clone()).expect("File Doesn't Exist");
-
     let state: &'static RwLock<State> = Box::leak(Box::new(RwLock::new(State::Normal)));
-    let root: &'static RwLock<Root> = Box::leak(Box::new(
-        RwLock::new(Root::new(SplitDirection::Vertical, terminal.get_frame().size()))
-    ));
-    root.write().await.add_window(SplitDirection::Vertical, 0);
-    root.write().await.children[0]
-        .write().await.add_text(content, 0);
+    let redraw: &'static Notify = Box::leak(Box::new(Notify::new()));
+    // the config was checked when it was loaded, so its bindings all make sense
+    let keymaps = Keymaps::with(config()).unwrap();
+    let keymaps: &'static RwLock<Keymaps> = Box::leak(Box::new(RwLock::new(keymaps)));
+    let area = terminal.get_frame().size();
+    let root = Arc::new(RwLock::new(Root::new(split_dir, area)));
+    let tabs: &'static RwLock<Tabs> = Box::leak(Box::new(RwLock::new(Tabs::new(area, root.clone()))));
+
+    // one window per file, each showing its own buffer
+    let mut opened = Vec::with_capacity(texts.len());
+    for (i, text) in texts.into_iter().enumerate() {
+        let view = tabs.write().await.buffers.add(text);
+        opened.push(view.text.clone());
+        root.write().await.add_window(split_dir, i);
+        root.read().await.children[i].write().await.add_view(view, 0);
+    }
+    tabs.read().await.layout().await;
+    if !config_errors.is_empty() {
+        tabs.write().await.post(Level::Error, config_errors.join("\n"));
+    }
+
+    //
+    // stdin thread:
+    //     *  only when paging
+    //     1. reads stdin a line at a time
+    //     2. appends each line to the text it was opened into
+    //
+
+    if streamed {
+        let index = cli.paths.iter().position(|p| p == Path::new(STDIN)).unwrap_or(0);
+        stdin_thread_init(opened[index].clone(), tabs, redraw);
+    }
 
     let (input_tx, input_rx) = mpsc::unbounded_channel::<Command>();
-    let (render_tx, mut render_rx) = mpsc::unbounded_channel::<WindowRender>();
+    let (render_tx, mut render_rx) = mpsc::unbounded_channel::<TabsRender>();
 
     //
     // input thread:
-    //     1. polls input from the terminal
-    //     2. converts them into commands
+    //     1. awaits events from the terminal
+    //     2. looks them up in the current mode's keymap
     //     3. sends the commands to the control thread
     //
 
-    input_thread_init(state, root, input_tx, CONTROL_DEADLINE);
+    input_thread_init(state, keymaps, input_tx);
 
     //
     // control thread:
     //     1. receives commands from input thread
     //     2. executes users commands through zippers
     //     3. zippers modify the atomic tree
+    //     4. asks the build thread for a new frame
     //
 
-    control_thread_init(state, root, input_rx);
+    control_thread_init(state, tabs, keymaps, redraw, input_rx);
 
     //
     // build thread:
-    //     1. asynchronously traverses the atomic tree
-    //     2. builds a render of the current state of the atomic tree
-    //     3. sends the render to the render thread
+    //     1. sleeps until something asks for a redraw
+    //     2. asynchronously traverses the atomic tree
+    //     3. builds a render of the current state of the atomic tree
+    //     4. sends the render to the render thread
+    //     *  requests made while a frame is being built or inside the frame
+    //        budget are coalesced into the next frame
     //
 
+    redraw.notify_one();
     tokio::spawn(async move {
-        let tick_rate = Duration::from_nanos(RENDER_DEADLINE);
+        let tick_rate = Duration::from_nanos(BILLIE / config().fps_limit);
         let mut last_tick = Instant::now();
+        let mut last_render: Option<(Rect, TabsRender)> = None;
         loop {
+            redraw.notified().await;
             if *state.read().await == State::ShutDown { break }
-            
-            render_tx.send(root.async_render().await).unwrap();
+
+            // nothing changed since the last frame, so there's nothing to draw
+            let area = tabs.read().await.area;
+            let render = tabs.async_render().await;
+            let unchanged = last_render
+                .as_ref()
+                .is_some_and(|(last_area, last)| *last_area == area && last.same_as(&render));
+            if !unchanged {
+                last_render = Some((area, render.clone()));
+                render_tx.send(render).unwrap();
+            }
 
             last_tick = timeout_sleep(tick_rate, last_tick).await;
         }
@@ -0,0 +1,87 @@
+use ratatui::style::Style;
+
+use crate::theme::theme;
+
+const HISTORY_LIMIT: usize = 500;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn style(&self) -> Style {
+        let theme = theme();
+        match self {
+            Level::Info => theme.info.style(),
+            Level::Warning => theme.warning.style(),
+            Level::Error => theme.error.style(),
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Message {
+    pub level: Level,
+    pub text: String,
+}
+
+#[derive(Default, Clone)]
+pub struct Messages {
+    current: Option<Message>,
+    history: Vec<Message>,
+    // messages too long for the message line, waiting to be opened in a pager window
+    pub page: Option<String>,
+}
+
+impl Messages {
+    // shows the message until the next command and keeps it in the history.
+    // anything that won't fit in `width` columns is also queued up for a pager window
+    pub fn post<T: Into<String>>(&mut self, level: Level, text: T, width: u16) {
+        let text: String = text.into();
+        let first = text.lines().next().unwrap_or("").to_string();
+        let long = text.contains('\n') || text.chars().count() > width as usize;
+        if long { self.page = Some(text.clone()) }
+
+        self.current = Some(Message {
+            level,
+            text: if long { format!("{} (more in window)", first) } else { first },
+        });
+        self.history.push(Message { level, text });
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.remove(0);
+        }
+    }
+
+    pub fn current(&self) -> Option<&Message> {
+        self.current.as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    // the whole history, oldest first, one message after another
+    pub fn history(&self) -> String {
+        let mut out = String::new();
+        for message in self.history.iter() {
+            out.push_str(&format!("{}: {}\n", message.level.tag(), message.text));
+        }
+        if out.is_empty() { out.push('\n') }
+        out
+    }
+}
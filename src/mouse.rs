@@ -0,0 +1,163 @@
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use either::*;
+use ratatui::{layout::{Constraint, Rect}, widgets::Block};
+
+use crate::{
+    config::config,
+    primatives::{split_area, Root, SplitDirection, View},
+    windows,
+    zipper::{self, DynZipper},
+    ARW
+};
+
+//
+// mouse events, mapped back through the layout to whatever is under them. a
+// click focuses it, the wheel scrolls the view under the mouse, and dragging
+// selects text or moves the separator between two panes
+//
+
+const WHEEL_LINES: usize = 3;
+
+// what the button went down on, kept until it comes back up
+pub enum Drag {
+    Select { view: ARW<View>, pane: Rect, anchor: (usize, usize) },
+    Separator { path: Vec<usize>, index: usize, split_dir: SplitDirection, start: u16 },
+}
+
+enum Target {
+    Pane { path: Vec<usize>, view: ARW<View>, pane: Rect }, // the path leads to the view
+    // between the pane at `index` and the one after it. `start` is where that pane begins
+    Separator { path: Vec<usize>, index: usize, split_dir: SplitDirection, start: u16 },
+}
+
+fn inside(area: Rect, x: u16, y: u16) -> bool {
+    (area.left()..area.right()).contains(&x) && (area.top()..area.bottom()).contains(&y)
+}
+
+// walks down the tree dividing the areas the same way layout does
+async fn target(root: &ARW<Root>, x: u16, y: u16) -> Option<Target> {
+    let (mut split_dir, mut sizes, mut area, mut children, mut borders) = {
+        let root = root.read().await;
+        let children: Vec<_> = root.children.iter().cloned().map(Left).collect();
+        (root.split_dir, root.sizes(), root.area, children, false)
+    };
+    let mut path = Vec::new();
+    loop {
+        let areas = split_area(split_dir, &sizes, area);
+        if let Some(index) = areas.iter().position(|a| inside(*a, x, y)) {
+            path.push(index);
+            match children[index].clone() {
+                Left(window) => {
+                    let window = window.read().await;
+                    (split_dir, sizes, area, borders) = (window.split_dir, window.sizes(), areas[index], window.borders);
+                    children = window.children.clone();
+                },
+                Right(view) => {
+                    let pane = if borders { Block::bordered().inner(areas[index]) } else { areas[index] };
+                    return Some(Target::Pane { path, view, pane })
+                },
+            }
+            continue
+        }
+
+        // the gap in front of a pane separates it from the one before
+        let index = areas.iter().skip(1).position(|a| match split_dir {
+            SplitDirection::Vertical => a.x == x + 1 && (a.top()..a.bottom()).contains(&y),
+            SplitDirection::Horizontal => a.y == y + 1 && (a.left()..a.right()).contains(&x),
+        })?;
+        let start = match split_dir {
+            SplitDirection::Vertical => areas[index].x,
+            SplitDirection::Horizontal => areas[index].y,
+        };
+        return Some(Target::Separator { path, index, split_dir, start })
+    }
+}
+
+// the row and column of the text under a cell, the column being none over the
+// line numbers. nothing past the last line or on the title row
+async fn position(view: &ARW<View>, pane: Rect, x: u16, y: u16) -> Option<(usize, Option<usize>)> {
+    if !inside(pane, x, y) || y + 1 >= pane.bottom() { return None }
+    let view = view.read().await;
+    let row = view.scroll_offset + (y - pane.y) as usize;
+    let text = view.text.read().await;
+    if row >= text.line_count() { return None }
+    let gutter = config().gutter.min(pane.width);
+    if x < pane.x + gutter { return Some((row, None)) }
+    // past the end of the line is the last character
+    let len = text.line_len(row);
+    Some((row, Some(((x - pane.x - gutter) as usize).min(len.saturating_sub(1)))))
+}
+
+// like position, but anywhere on screen is taken to the nearest character
+async fn nearest(view: &ARW<View>, pane: Rect, x: u16, y: u16) -> (usize, usize) {
+    let view = view.read().await;
+    let rows = pane.height.saturating_sub(1).max(1);
+    let y = y.clamp(pane.top(), pane.top() + rows - 1);
+    let text = view.text.read().await;
+    let row = (view.scroll_offset + (y - pane.y) as usize).min(text.line_count().saturating_sub(1));
+    let gutter = config().gutter.min(pane.width);
+    let column = x.saturating_sub(pane.x + gutter) as usize;
+    (row, column.min(text.line_len(row).saturating_sub(1)))
+}
+
+pub async fn handle(root: &ARW<Root>, zipper: &DynZipper, event: MouseEvent, drag: &mut Option<Drag>) -> Option<DynZipper> {
+    let (x, y) = (event.column, event.row);
+    match event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            *drag = None;
+            match target(root, x, y).await? {
+                Target::Pane { path, view, pane } => {
+                    view.write().await.selection = None;
+                    let at = position(&view, pane, x, y).await;
+                    zipper.highlight(false).await;
+                    let focus = zipper::from_path(root, &path).await;
+                    let text = focus.text()?;
+                    let focus = match at {
+                        Some((row, Some(column))) => text.cursor_at(row, column).await,
+                        Some((row, None)) => text.line_at(row).await,
+                        None => focus,
+                    };
+                    *drag = at.map(|(row, column)| Drag::Select { view, pane, anchor: (row, column.unwrap_or(0)) });
+                    Some(focus)
+                },
+                Target::Separator { path, index, split_dir, start } => {
+                    *drag = Some(Drag::Separator { path, index, split_dir, start });
+                    None
+                },
+            }
+        },
+        MouseEventKind::Drag(MouseButton::Left) => match drag.as_ref()? {
+            Drag::Select { view, pane, anchor } => {
+                let here = nearest(view, *pane, x, y).await;
+                let (start, end) = if here < *anchor { (here, *anchor) } else { (*anchor, here) };
+                view.write().await.selection = Some((start, end));
+                None
+            },
+            Drag::Separator { path, index, split_dir, start } => {
+                let at = match split_dir {
+                    SplitDirection::Vertical => x,
+                    SplitDirection::Horizontal => y,
+                };
+                let size = at.saturating_sub(*start).max(1);
+                windows::set_size(root, path, *index, Constraint::Length(size)).await;
+                None
+            },
+        },
+        MouseEventKind::Up(MouseButton::Left) => {
+            // the selection stays behind for whatever comes next
+            *drag = None;
+            None
+        },
+        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+            let Target::Pane { view, .. } = target(root, x, y).await? else { return None };
+            let mut view = view.write().await;
+            let last = view.text.read().await.line_count().saturating_sub(1);
+            view.scroll_offset = match event.kind {
+                MouseEventKind::ScrollDown => (view.scroll_offset + WHEEL_LINES).min(last),
+                _ => view.scroll_offset.saturating_sub(WHEEL_LINES),
+            };
+            None
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,34 @@
+use tokio::{io::{stdin, AsyncBufReadExt, BufReader}, sync::{Notify, RwLock}, task::JoinHandle};
+
+use crate::{messages::Level, primatives::Text, tabs::Tabs, ARW};
+
+//
+// appends stdin to the text a line at a time as it arrives, so a pager
+// doesn't have to wait for the command feeding it to finish
+//
+
+pub fn stdin_thread_init(
+    text: ARW<Text>,
+    tabs: &'static RwLock<Tabs>,
+    redraw: &'static Notify,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stdin());
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line).await {
+                Ok(0) => break,
+                Err(e) => {
+                    tabs.write().await.post(Level::Error, format!("stopped reading stdin: {}", e));
+                    redraw.notify_one();
+                    break
+                },
+                Ok(_) => {
+                    text.write().await.append(&String::from_utf8_lossy(&line)).await;
+                    redraw.notify_one();
+                },
+            }
+        }
+    })
+}
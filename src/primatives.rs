@@ -1,13 +1,16 @@
-use std::{cmp::min, sync::Arc, u16, usize};
+use std::{cmp::min, collections::BTreeMap, sync::atomic::{AtomicU64, Ordering}, fs::{self, File}, io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write}, ops::Range, path::{Path, PathBuf}, sync::Arc};
 
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use either::*;
+use ropey::{Rope, RopeBuilder};
+use serde::Deserialize;
 use tokio::{sync::RwLock, task::JoinSet};
 use ratatui::{
-    buffer::Buffer, layout::{Alignment, Position, Rect}, style::{Color, Style}, widgets::{Clear, WidgetRef}
+    buffer::Buffer, layout::{Alignment, Constraint, Direction, Layout, Rect}, style::Style, widgets::{Block, Clear, WidgetRef}
 };
 
-use crate::ARW;
+use crate::{buffers::BufferId, config::config, encoding::{Decoder, Encoding}, theme::{self, theme}, ARW};
 
 #[async_trait]
 pub trait AsyncWidget {
@@ -16,31 +19,103 @@ pub trait AsyncWidget {
     async fn no_highlight(&self) {}
 }
 
-#[derive(Default, Clone, PartialEq, Eq)]
+// versions come from one counter so a rebuilt node never reuses an old version
+static VERSION: AtomicU64 = AtomicU64::new(1);
+
+fn next_version() -> u64 {
+    VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
 pub struct Char {
     pub char: char,
     pub style: Style,
 }
 
-#[derive(Default, Clone)]
+// a run of `len` characters sharing a style
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct StyleRun {
+    pub len: usize,
+    pub style: Style,
+}
+
+// spans aren't stored. they're character ranges of their line, split after each space
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
 pub struct Span {
-    pub characters: Vec<ARW<Char>>,
+    pub start: usize,
+    pub len: usize,
 }
 
+// the text of a line is kept contiguous, styles are run-length encoded over it.
+// the runs always add up to the number of characters in the line
 #[derive(Default, Clone)]
 pub struct Line {
-    pub spans: Vec<ARW<Span>>,
+    pub text: String,
+    pub styles: Vec<StyleRun>,
+    pub version: u64,
+    render: Option<LineRender>, // cleared whenever the line changes
 }
 
+// the rope holds the document. lines are only built into nodes when they
+// scroll into view or a zipper descends into them, and are kept in step with
+// the rope by the editing methods on Text
 #[derive(Default, Clone)]
 pub struct Text {
+    pub path: Option<PathBuf>,
+    pub encoding: Encoding,
+    pub rope: Rope,
+    pub lines: BTreeMap<usize, ARW<Line>>,
+    pub alignment: Option<Alignment>,
+    pub changes: u64,
+    pub saved_changes: u64,
+    pub read_only: bool,
+    pub title: Option<String>, // shown in place of the path for texts without one
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+// one undoable change to the rope, at a character index
+#[derive(Default, Clone)]
+struct Edit {
+    at: usize,
+    removed: String,
+    inserted: String,
+}
+
+// a window's look into a text. every view of a text shares its lines, so an
+// edit through one shows up in the others, but each scrolls on its own
+#[derive(Default, Clone)]
+pub struct View {
+    pub buffer: BufferId,
+    pub text: ARW<Text>,
     pub scroll_offset: usize,
     pub height: usize,
-    pub lines: Vec<ARW<Line>>,
-    pub alignment: Option<Alignment>,
+    pub cursor: (usize, usize), // row and column the focus was last at
+    pub highlight: Option<(usize, Range<usize>)>, // drawn over the line, the text's styles aren't touched
+    pub selection: Option<((usize, usize), (usize, usize))>, // first and last row and column, both included
+    pub focused: bool, // holds the zipper's focus, set by the control thread
+    visible: Vec<ARW<Line>>, // kept so other views of the text don't unload them
+    render: Option<(TextRenderKey, TextRender)>,
+}
+
+// a text render can be reused while none of these have changed
+#[derive(Default, Clone, PartialEq, Eq)]
+struct TextRenderKey {
+    scroll_offset: usize,
+    height: usize,
+    versions: Vec<u64>,
+    name: String,
+    modified: bool,
+    read_only: bool,
+    focused: bool,
+    highlight: Option<(usize, Range<usize>)>,
+    selection: Option<((usize, usize), (usize, usize))>,
+    theme: u64,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SplitDirection {
     #[default]
     Vertical,
@@ -52,6 +127,7 @@ pub struct Root {
     pub area: Rect,
     pub split_dir: SplitDirection,
     pub children: Vec<ARW<Window>>,
+    pub sizes: Vec<Constraint>, // one per child, missing ones fill an equal share
 }
 
 impl Root {
@@ -60,18 +136,40 @@ impl Root {
             split_dir,
             area,
             children: Vec::new(),
+            sizes: Vec::new(),
         }
     }
 
     pub fn add_window(&mut self, split_dir: SplitDirection, index: usize) {
-        if index >= self.children.len() {
-            self.children.push(Arc::new(RwLock::new(Window::new(split_dir, self.area))));
+        let index = min(index, self.children.len());
+        insert_size(&mut self.sizes, index);
+        self.children.insert(index, Arc::new(RwLock::new(Window::new(split_dir, self.area))));
+    }
+
+    // pushes the area down to every window and text, divided the same way it's rendered
+    pub async fn layout(&self) {
+        let areas = split_area(self.split_dir, &self.sizes(), self.area);
+        let mut stack: Vec<(ARW<Window>, Rect)> = self.children.iter().cloned().zip(areas.iter().copied()).collect();
+        while let Some((window, area)) = stack.pop() {
+            let mut window = window.write().await;
+            window.area = area;
+            let areas = split_area(window.split_dir, &window.sizes(), area);
+            for (child, area) in window.children.iter().zip(areas.iter()) {
+                match child {
+                    Left(window) => stack.push((window.clone(), *area)),
+                    Right(view) => view.write().await.height = window.pane(*area).height.into(),
+                }
+            }
         }
-        let mut children = self.children.clone();
-        let mut tmp = children.drain(index..).collect();
-        children.push(Arc::new(RwLock::new(Window::new(split_dir, self.area))));
-        children.append(&mut tmp);
-        self.children = children;
+    }
+
+    pub fn remove_window(&mut self, index: usize) -> ARW<Window> {
+        if index < self.sizes.len() { self.sizes.remove(index); }
+        self.children.remove(index)
+    }
+
+    pub fn sizes(&self) -> Vec<Constraint> {
+        padded_sizes(&self.sizes, self.children.len())
     }
 }
 
@@ -79,7 +177,9 @@ impl Root {
 pub struct Window {
     pub area: Rect,
     pub split_dir: SplitDirection,
-    pub children: Vec<Either<ARW<Window>, ARW<Text>>>,
+    pub children: Vec<Either<ARW<Window>, ARW<View>>>,
+    pub sizes: Vec<Constraint>,
+    pub borders: bool, // a titled border around each of its texts
 }
 
 impl Window {
@@ -88,232 +188,635 @@ impl Window {
             split_dir,
             area,
             children: Vec::new(),
+            sizes: Vec::new(),
+            borders: config().borders,
         }
     }
 
+    // where a text goes inside the area given to it
+    pub fn pane(&self, area: Rect) -> Rect {
+        if self.borders { Block::bordered().inner(area) } else { area }
+    }
+
     pub fn add_window(&mut self, split_dir: SplitDirection, index: usize) {
-        if index >= self.children.len() {
-            self.children.push(Left(Arc::new(RwLock::new(Window::new(split_dir, self.area)))));
-        }
-        let mut children = self.children.clone();
-        let mut tmp = children.drain(index..).collect();
-        children.push(Left(Arc::new(RwLock::new(Window::new(split_dir, self.area)))));
-        children.append(&mut tmp);
-        self.children = children;
+        let index = min(index, self.children.len());
+        insert_size(&mut self.sizes, index);
+        self.children.insert(index, Left(Arc::new(RwLock::new(Window::new(split_dir, self.area)))));
     }
 
-    pub fn add_text(&mut self, content: String, index: usize) {
-        let mut text = Text::raw(content);
-        text.height = self.area.height.into();
-        if index >= self.children.len() {
-            self.children.push(Right(Arc::new(RwLock::new(text))));
-        } else {
-            let mut children = self.children.clone();
-            let mut tmp = children.drain(index..).collect();
-            children.push(Right(Arc::new(RwLock::new(text))));
-            children.append(&mut tmp);
+    pub fn add_view(&mut self, mut view: View, index: usize) -> ARW<View> {
+        view.height = self.pane(self.area).height.into();
+        let view = Arc::new(RwLock::new(view));
+        self.insert_view(view.clone(), index);
+        view
+    }
 
-        }
+    // for a view that's moving from somewhere else in the tree
+    pub fn insert_view(&mut self, view: ARW<View>, index: usize) {
+        let index = min(index, self.children.len());
+        insert_size(&mut self.sizes, index);
+        self.children.insert(index, Right(view));
+    }
+
+    pub fn remove_child(&mut self, index: usize) -> Either<ARW<Window>, ARW<View>> {
+        if index < self.sizes.len() { self.sizes.remove(index); }
+        self.children.remove(index)
+    }
+
+    pub fn sizes(&self) -> Vec<Constraint> {
+        padded_sizes(&self.sizes, self.children.len())
     }
 }
 
-impl Span { 
-    pub fn raw<T: Into<String>>(content: T) -> Span {
-        let content: String = content.into();
-        Span {
-            characters: content
-                .chars()
-                .map(|ch| Arc::new(RwLock::new(
-                    Char { char: ch, style: Style::default() }
-                )))
-                .collect(),
+fn insert_size(sizes: &mut Vec<Constraint>, index: usize) {
+    if index <= sizes.len() { sizes.insert(index, Constraint::Fill(1)) }
+}
+
+fn padded_sizes(sizes: &[Constraint], len: usize) -> Vec<Constraint> {
+    (0..len).map(|i| sizes.get(i).copied().unwrap_or(Constraint::Fill(1))).collect()
+}
+
+// the areas of a window's children, with a cell between each for a separator
+pub fn split_area(split_dir: SplitDirection, sizes: &[Constraint], area: Rect) -> Vec<Rect> {
+    let direction = match split_dir {
+        SplitDirection::Horizontal => Direction::Vertical,
+        SplitDirection::Vertical => Direction::Horizontal,
+    };
+    Layout::new(direction, sizes.iter().copied()).spacing(1).split(area).to_vec()
+}
+
+// depth-first, in on-screen order
+pub async fn descendant_views(windows: Vec<ARW<Window>>) -> Vec<ARW<View>> {
+    let mut stack: Vec<ARW<Window>> = windows.into_iter().rev().collect();
+    let mut views = Vec::new();
+    while let Some(window) = stack.pop() {
+        let mut windows = Vec::new();
+        for child in window.read().await.children.iter().cloned() {
+            match child {
+                Left(window) => windows.push(window),
+                Right(view) => views.push(view),
+            }
         }
+        stack.extend(windows.into_iter().rev());
     }
+    views
+}
 
-    pub async fn is_newline(&self) -> bool {
-        if self.characters.len() == 1
-            && self.characters[0].read().await.char == b'\n' as char
-        {
-            return true;
-        }
-        false
+// a text shown in several views comes up once for each
+pub async fn descendant_texts(windows: Vec<ARW<Window>>) -> Vec<ARW<Text>> {
+    let mut texts = Vec::new();
+    for view in descendant_views(windows).await {
+        texts.push(view.read().await.text.clone());
+    }
+    texts
+}
+
+// none when the bytes aren't valid in the encoding
+fn read_rope(reader: &mut impl BufRead, encoding: Encoding) -> Result<Option<Rope>> {
+    let mut decoder = Decoder::new(encoding);
+    let mut builder = RopeBuilder::new();
+    loop {
+        let chunk = reader.fill_buf()?;
+        let (len, last) = (chunk.len(), chunk.is_empty());
+        let Some(content) = decoder.decode(chunk, last) else { return Ok(None) };
+        builder.append(&content);
+        reader.consume(len);
+        if last { return Ok(Some(builder.finish())) }
+    }
+}
 
+impl Span {
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.start + self.len
     }
 }
 
 impl Text {
     pub fn raw(input: String) -> Text {
         Text {
-            lines: input
-                .split_inclusive('\n')
-                .map(|ln| Arc::new(RwLock::new(Line::raw(ln))))
-                .collect(),
+            rope: Rope::from_str(&input),
             ..Default::default()
         }
     }
 
-    pub fn add_line(&mut self, line: ARW<Line>, index: usize) {
-        let len = self.lines.len();
-        let mut lines: Vec<ARW<Line>> =
-            self.lines
-                .drain(min(index, len)..len)
-                .collect();
-
-        self.lines.push(line);
-        self.lines.append(&mut lines);
+    // a lone newline, so there's always a character to type in front of
+    pub fn empty() -> Text {
+        Text::raw("\n".to_string())
+    }
 
-        self.lines = lines;
+    // a path that doesn't exist yet opens an empty text that creates it when saved
+    pub fn open(path: &Path) -> Result<Text> {
+        match Text::read(path) {
+            Err(e) if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::NotFound) => {
+                Ok(Text {
+                    path: Some(path.to_path_buf()),
+                    ..Text::empty()
+                })
+            },
+            result => result.with_context(|| format!("couldn't read {}", path.display())),
+        }
     }
 
-    pub fn get_line(&self, index: usize) -> ARW<Line> {
-        self.lines.get(index)
-            .unwrap_or(
-                self.lines.get(self.lines.len() - 1).unwrap()
-            ).clone()
+    pub fn from_reader(mut reader: impl Read) -> Result<Text> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let (content, encoding) = Encoding::decode(&bytes);
+        Ok(Text {
+            encoding,
+            ..Text::raw(content)
+        })
     }
-}
 
-impl Line {
-    pub fn raw<T: Into<String>>(input: T) -> Line {
-        let spans: String = input.into();
-        Line {
-            spans: spans
-                .split_inclusive(' ')
-                .map(|sp| Arc::new(RwLock::new(Span::raw(sp))))
-                .collect(),
+    // the file is streamed into the rope, so it's never held in memory as one piece
+    fn read(path: &Path) -> Result<Text> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let encoding = Encoding::sniff(reader.fill_buf()?);
+        reader.consume(encoding.bom().len());
+
+        // bytes that turn out not to be utf-8 are read again from the start as latin-1
+        let (rope, encoding) = match read_rope(&mut reader, encoding)? {
+            Some(rope) => (rope, encoding),
+            None => {
+                reader.seek(SeekFrom::Start(0))?;
+                (read_rope(&mut reader, Encoding::Latin1)?.unwrap_or_default(), Encoding::Latin1)
+            },
+        };
+
+        Ok(Text {
+            path: Some(path.to_path_buf()),
+            encoding,
+            rope,
             ..Default::default()
+        })
+    }
+
+    pub fn contents(&self) -> String {
+        self.rope.to_string()
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.changes != self.saved_changes
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        let Some(ref path) = self.path else { bail!("no file name") };
+        self.write_to(path)?;
+        self.saved_changes = self.changes;
+        Ok(())
+    }
+
+    // writes a copy without touching the text's own path or modified flag. it goes
+    // to a file next to `path` first, which only replaces `path` once it's all written
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let name = path.file_name().with_context(|| format!("can't write to {}", path.display()))?;
+        let temp = path.with_file_name(format!(".{}.bespoke-ed~", name.to_string_lossy()));
+        let written = self.write_whole(&temp).and_then(|()| {
+            // the file keeps its permissions
+            if let Ok(metadata) = fs::metadata(path) { fs::set_permissions(&temp, metadata.permissions())? }
+            Ok(fs::rename(&temp, path)?)
+        });
+        if written.is_err() { let _ = fs::remove_file(&temp); }
+        written.with_context(|| format!("couldn't write {}", path.display()))
+    }
+
+    fn write_whole(&self, path: &Path) -> Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(self.encoding.bom())?;
+        for chunk in self.rope.chunks() {
+            file.write_all(&self.encoding.encode(chunk)?)?;
         }
+        file.into_inner()?.sync_all()?;
+        Ok(())
     }
-    pub fn add_span(&mut self, span: ARW<Span>, index: usize) {
-        let len = self.spans.len();
-        let mut spans: Vec<ARW<Span>> =
-            self.spans
-                .drain(min(index, len)..len)
-                .collect();
 
-        self.spans.push(span);
-        self.spans.append(&mut spans);
+    pub fn name(&self) -> String {
+        match (self.path.as_ref(), self.title.as_ref()) {
+            (Some(path), _) => path.display().to_string(),
+            (None, Some(title)) => title.clone(),
+            (None, None) => "[scratch]".to_string(),
+        }
+    }
 
-        self.spans = spans;
+    // a trailing newline doesn't start another line
+    pub fn line_count(&self) -> usize {
+        let count = self.rope.len_lines();
+        if count > 1 && self.rope.line(count - 1).len_chars() == 0 { count - 1 } else { count }
     }
 
-    pub async fn char_len(&self) -> u16 {
-        let mut set = JoinSet::new();
+    pub fn line(&mut self, index: usize) -> ARW<Line> {
+        let index = min(index, self.line_count() - 1);
+        let rope = &self.rope;
+        self.lines
+            .entry(index)
+            .or_insert_with(|| Arc::new(RwLock::new(Line::raw(rope.line(index)))))
+            .clone()
+    }
+
+    // drop the nodes nobody else is holding on to outside of `keep`
+    pub fn unload_lines(&mut self, keep: Range<usize>) {
+        self.lines.retain(|i, line| keep.contains(i) || Arc::strong_count(line) > 1);
+    }
+
+    // characters in the line, not counting its newline
+    pub fn line_len(&self, row: usize) -> usize {
+        if row >= self.rope.len_lines() { return 0 }
+        self.rope.line(row).chars().take_while(|&ch| ch != '\n').count()
+    }
 
-        for sp in self.spans.iter().cloned() {
-            set.spawn(async move { sp.read().await.characters.len() });
+    fn char_index(&self, row: usize, column: usize) -> usize {
+        let row = min(row, self.rope.len_lines() - 1);
+        self.rope.line_to_char(row) + min(column, self.line_len(row))
+    }
+
+    async fn reload_line(&mut self, row: usize) {
+        if let Some(line) = self.lines.get(&row) {
+            *line.write().await = Line::raw(self.rope.line(row));
         }
+    }
 
-        let mut count = 0;
-        while let Some(Ok(len)) = set.join_next().await {
-            count += len;
+    // moves every loaded line after `row` by `by` rows
+    fn shift_lines(&mut self, row: usize, by: isize) {
+        let moved = self.lines.split_off(&(row + 1));
+        for (i, line) in moved {
+            let Some(i) = i.checked_add_signed(by) else { continue };
+            if i > row { self.lines.insert(i, line); }
         }
-        count as u16
     }
-}
 
-#[async_trait]
-impl AsyncWidget for ARW<Char> {
-    async fn async_render(&self) -> Char {
-        self.read().await.clone()
+    // adds to the end without counting as a change, for text that's still arriving
+    pub async fn append(&mut self, content: &str) {
+        let last = self.rope.len_lines() - 1;
+        self.rope.insert(self.rope.len_chars(), content);
+        self.reload_line(last).await;
+    }
+
+    // a whole paste goes in at once, as one change to undo
+    pub async fn insert(&mut self, row: usize, column: usize, content: &str) {
+        let at = self.char_index(row, column);
+        self.rope.insert(at, content);
+        self.record(Edit { at, inserted: content.to_string(), ..Default::default() });
+
+        // a single character is put into the line, anything longer rebuilds it
+        let newlines = content.matches('\n').count();
+        if newlines == 0 && content.chars().count() == 1 {
+            if let Some(line) = self.lines.get(&row) {
+                let mut line = line.write().await;
+                for (i, ch) in content.chars().enumerate() {
+                    line.insert(column + i, ch);
+                }
+            }
+            return
+        }
+        self.shift_lines(row, newlines as isize);
+        for row in row..=row + newlines {
+            self.reload_line(row).await;
+        }
     }
 
-    async fn highlight(&self) {
-        let mut wg = self.write().await;
-        wg.style.bg = Some(Color::White);
-        wg.style.fg = Some(Color::Black);
+    pub async fn remove(&mut self, row: usize, column: usize) -> Option<char> {
+        let at = self.char_index(row, column);
+        let ch = self.rope.get_char(at)?;
+        self.rope.remove(at..at + 1);
+        self.record(Edit { at, removed: ch.to_string(), ..Default::default() });
+
+        if ch == '\n' {
+            self.lines.remove(&(row + 1));
+            self.shift_lines(row, -1);
+            self.reload_line(row).await;
+        } else if let Some(line) = self.lines.get(&row) {
+            line.write().await.remove(column);
+        }
+        Some(ch)
     }
-    async fn no_highlight(&self) {
-        self.write().await.style = Style::default();
+
+    // removes whole lines, leaving an empty one behind if that was all of them
+    pub fn remove_lines(&mut self, rows: Range<usize>) {
+        let start = self.rope.line_to_char(min(rows.start, self.rope.len_lines()));
+        let end = self.rope.line_to_char(min(rows.end, self.rope.len_lines()));
+        if start == end { return }
+        let removed = self.rope.slice(start..end).to_string();
+        self.rope.remove(start..end);
+        let inserted = if self.rope.len_chars() == 0 { "\n" } else { "" };
+        self.rope.insert(0, inserted);
+        self.record(Edit { at: start, removed, inserted: inserted.to_string() });
+
+        let count = rows.len();
+        let moved = self.lines.split_off(&rows.start);
+        for (i, line) in moved {
+            if i >= rows.end { self.lines.insert(i - count, line); }
+        }
     }
-}
 
-#[async_trait]
-impl AsyncWidget for ARW<Span> {
-    async fn async_render(&self) -> SpanRender {
-        let mut set = JoinSet::new();
-        for (i, char) in self.read().await.characters.iter().cloned().enumerate() {
-            set.spawn(async move { (i, char.async_render().await) });
+    // removes everything from one row and column to another, both included
+    pub async fn remove_chars(&mut self, first: (usize, usize), last: (usize, usize)) {
+        let start = self.char_index(first.0, first.1);
+        let end = (self.char_index(last.0, last.1) + 1).min(self.rope.len_chars());
+        if start >= end { return }
+        let removed = self.rope.slice(start..end).to_string();
+        let inserted = if end - start == self.rope.len_chars() { "\n" } else { "" };
+        self.splice(start, end - start, inserted).await;
+        self.record(Edit { at: start, removed, inserted: inserted.to_string() });
+    }
+
+    fn record(&mut self, edit: Edit) {
+        // a new change drops the undone ones, along with the saved state if it was one of them
+        if !self.redo.is_empty() && self.saved_changes > self.changes { self.saved_changes = u64::MAX }
+        self.redo.clear();
+        self.undo.push(edit);
+        self.changes += 1;
+    }
+
+    // both give back the row and column the change was at
+    pub async fn undo(&mut self) -> Option<(usize, usize)> {
+        let edit = self.undo.pop()?;
+        self.splice(edit.at, edit.inserted.chars().count(), &edit.removed).await;
+        self.changes -= 1;
+        let at = edit.at;
+        self.redo.push(edit);
+        Some(self.position(at))
+    }
+
+    pub async fn redo(&mut self) -> Option<(usize, usize)> {
+        let edit = self.redo.pop()?;
+        self.splice(edit.at, edit.removed.chars().count(), &edit.inserted).await;
+        self.changes += 1;
+        let at = edit.at;
+        self.undo.push(edit);
+        Some(self.position(at))
+    }
+
+    fn position(&self, at: usize) -> (usize, usize) {
+        let row = self.rope.char_to_line(at.min(self.rope.len_chars()));
+        (row, at - self.rope.line_to_char(row))
+    }
+
+    // swaps `len` characters at `at` for `content`, rebuilding the loaded lines it touched
+    async fn splice(&mut self, at: usize, len: usize, content: &str) {
+        let row = self.rope.char_to_line(at);
+        let removed = self.rope.slice(at..at + len).chars().filter(|&ch| ch == '\n').count();
+        self.rope.remove(at..at + len);
+        self.rope.insert(at, content);
+
+        let added = content.matches('\n').count();
+        for gone in row + 1..=row + removed {
+            self.lines.remove(&gone);
         }
-        let mut characters = Vec::new();
-        while let Some(Ok(char)) = set.join_next().await {
-            characters.push(char);
+        self.shift_lines(row, added as isize - removed as isize);
+        for row in row..=row + added {
+            self.reload_line(row).await;
         }
-        characters.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+}
 
-        SpanRender {
-            characters: characters.into_iter().map(|(_, render)| render).collect(),
+impl View {
+    pub fn new(buffer: BufferId, text: ARW<Text>) -> View {
+        View {
+            buffer,
+            text,
             ..Default::default()
         }
     }
-    async fn highlight(&self) {
-        for char in self.read().await.characters.iter().cloned() {
-            tokio::spawn(async move { char.highlight().await });
+
+    // another view of the same text, starting out where this one is
+    pub fn split(&self) -> View {
+        View {
+            scroll_offset: self.scroll_offset,
+            cursor: self.cursor,
+            ..View::new(self.buffer, self.text.clone())
         }
     }
-    async fn no_highlight(&self) {
-        for char in self.read().await.characters.iter().cloned() {
-            tokio::spawn(async move { char.no_highlight().await });
+
+    // scrolls just far enough for `row` to be on screen, above the title row
+    pub fn scroll_to(&mut self, row: usize) {
+        let visible = self.height.saturating_sub(1).max(1);
+        if row < self.scroll_offset {
+            self.scroll_offset = row;
+        } else if row >= self.scroll_offset + visible {
+            self.scroll_offset = row + 1 - visible;
         }
     }
 }
 
-#[async_trait]
-impl AsyncWidget for ARW<Line> {
-    async fn async_render(&self) -> LineRender {
-        let mut set = JoinSet::new();
-        for (i, span) in self.read().await.spans.iter().cloned().enumerate() {
-            set.spawn(async move { (i, span.async_render().await) });
+impl Line {
+    pub fn raw<T: Into<String>>(input: T) -> Line {
+        let text: String = input.into();
+        let len = text.chars().count();
+        Line {
+            styles: if len > 0 { vec![StyleRun { len, style: Style::default() }] } else { Vec::new() },
+            text,
+            version: next_version(),
+            render: None,
         }
-        let mut spans = Vec::new();
-        while let Some(Ok(span)) = set.join_next().await {
-            spans.push(span);
+    }
+
+    fn touch(&mut self) {
+        self.version = next_version();
+        self.render = None;
+    }
+
+    pub fn char_len(&self) -> usize {
+        self.styles.iter().map(|run| run.len).sum()
+    }
+
+    pub fn spans(&self) -> Vec<Span> {
+        let mut start = 0;
+        self.text
+            .split_inclusive(' ')
+            .map(|sp| {
+                let span = Span { start, len: sp.chars().count() };
+                start += span.len;
+                span
+            })
+            .collect()
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = Char> + '_ {
+        let styles = self.styles
+            .iter()
+            .flat_map(|run| std::iter::repeat_n(run.style, run.len));
+        self.text
+            .chars()
+            .zip(styles)
+            .map(|(char, style)| Char { char, style })
+    }
+
+    pub fn char(&self, index: usize) -> Option<Char> {
+        self.chars().nth(index)
+    }
+
+    fn byte_index(&self, index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(index)
+            .map(|(i, _)| i)
+            .unwrap_or(self.text.len())
+    }
+
+    pub fn set_style(&mut self, range: Range<usize>, style: Style) {
+        let len = self.char_len();
+        let range = min(range.start, len)..min(range.end, len);
+        if range.is_empty() { return }
+
+        let mut styles = Vec::with_capacity(self.styles.len() + 2);
+        let mut inserted = false;
+        let mut start = 0;
+        for run in self.styles.iter() {
+            let end = start + run.len;
+            push_run(&mut styles, min(end, range.start).saturating_sub(start), run.style);
+            if !inserted && end >= range.start {
+                push_run(&mut styles, range.len(), style);
+                inserted = true;
+            }
+            push_run(&mut styles, end.saturating_sub(start.max(range.end)), run.style);
+            start = end;
         }
-        spans.sort_by(|a, b| a.0.cmp(&b.0));
+        if styles != self.styles {
+            self.styles = styles;
+            self.touch();
+        }
+    }
 
-        LineRender {
-            spans: spans.into_iter().map(|(_, render)| render).collect(),
-            ..Default::default()
+    pub fn clear_style(&mut self) {
+        let len = self.char_len();
+        self.set_style(0..len, Style::default());
+    }
+
+    pub fn insert(&mut self, index: usize, ch: char) {
+        let index = min(index, self.char_len());
+        let at = self.byte_index(index);
+        self.text.insert(at, ch);
+        self.touch();
+
+        // the new character takes the style of the one before it
+        let mut start = 0;
+        for run in self.styles.iter_mut() {
+            if index <= start + run.len && (index > start || start == 0) {
+                run.len += 1;
+                return
+            }
+            start += run.len;
         }
+        push_run(&mut self.styles, 1, Style::default());
     }
 
-    async fn highlight(&self) {
-        for span in self.read().await.spans.iter().cloned() {
-            tokio::spawn(async move { span.highlight().await });
+    pub fn remove(&mut self, index: usize) -> Option<char> {
+        if index >= self.char_len() { return None }
+        let ch = self.text.remove(self.byte_index(index));
+        self.touch();
+
+        let mut start = 0;
+        for i in 0..self.styles.len() {
+            if index < start + self.styles[i].len {
+                self.styles[i].len -= 1;
+                if self.styles[i].len == 0 { self.styles.remove(i); }
+                break
+            }
+            start += self.styles[i].len;
         }
+        Some(ch)
+    }
+}
+
+fn push_run(styles: &mut Vec<StyleRun>, len: usize, style: Style) {
+    if len == 0 { return }
+    match styles.last_mut() {
+        Some(last) if last.style == style => last.len += len,
+        _ => styles.push(StyleRun { len, style }),
     }
-    async fn no_highlight(&self) {
-        for span in self.read().await.spans.iter().cloned() {
-            tokio::spawn(async move { span.no_highlight().await });
+}
+
+#[async_trait]
+impl AsyncWidget for ARW<Line> {
+    async fn async_render(&self) -> LineRender {
+        if let Some(ref render) = self.read().await.render {
+            return render.clone()
         }
+        let mut line = self.write().await;
+        let render = LineRender {
+            text: line.text.as_str().into(),
+            styles: line.styles.as_slice().into(),
+            ..Default::default()
+        };
+        line.render = Some(render.clone());
+        render
     }
 }
 
 #[async_trait]
-impl AsyncWidget for ARW<Text> {
+impl AsyncWidget for ARW<View> {
     async fn async_render(&self) -> TextRender {
-        let copy = self.read().await.clone();
+        let mut view = self.write().await;
+        let text = view.text.clone();
+        let mut text = text.write().await;
 
-        let mut set = JoinSet::new();
-        for (i, line) in copy.lines.iter()
-            .cloned()
-            .enumerate()
-            .filter(|(i, _)| *i >= copy.scroll_offset && *i < copy.scroll_offset + copy.height - 1) 
-        {
-            let i = i + 1;
-            set.spawn(async move { (LineNumber::new(i), line.async_render().await) });
-        }
+        // the last row is kept for the title
+        let end = min(text.line_count(), view.scroll_offset + view.height.saturating_sub(1));
+        let start = min(view.scroll_offset, end);
+        view.visible = (start..end).map(|i| text.line(i)).collect();
+        text.unload_lines(start..end);
 
-        let mut lines = Vec::new();
-        while let Some(Ok(line)) = set.join_next().await {
-            lines.push(line);
+        let mut versions = Vec::with_capacity(view.visible.len());
+        for line in view.visible.iter() {
+            versions.push(line.read().await.version);
+        }
+        let key = TextRenderKey {
+            scroll_offset: view.scroll_offset,
+            height: view.height,
+            versions,
+            name: text.name(),
+            modified: text.is_modified(),
+            read_only: text.read_only,
+            focused: view.focused,
+            highlight: view.highlight.clone(),
+            selection: view.selection,
+            theme: theme::generation(),
+        };
+        if let Some((ref cached, ref render)) = view.render {
+            if *cached == key { return render.clone() }
         }
-        lines.sort_by(|a, b| a.0.cmp(&b.0));
 
-        TextRender { 
-            lines,
-            alignment: copy.alignment.clone(),
+        let mut lines = Vec::with_capacity(view.visible.len());
+        let theme = theme::theme();
+        for (i, line) in view.visible.iter().enumerate() {
+            let row = start + i;
+            let highlight = view.highlight.as_ref().filter(|(r, _)| *r == row).map(|(_, columns)| columns.clone());
+            let selected = view.selection.filter(|(first, last)| (first.0..=last.0).contains(&row));
+            let render = if highlight.is_none() && selected.is_none() {
+                line.async_render().await
+            } else {
+                // highlighted lines are styled on a copy so the cached render stays plain
+                let mut line = line.read().await.clone();
+                if let Some((first, last)) = selected {
+                    let from = if row == first.0 { first.1 } else { 0 };
+                    let to = if row == last.0 { last.1 + 1 } else { line.char_len() };
+                    line.set_style(from..to, theme.selection.style());
+                }
+                if let Some(columns) = highlight {
+                    // the focus can sit just past the last character, which shows as a blank
+                    if columns.start == line.char_len() {
+                        line.text.push(' ');
+                        push_run(&mut line.styles, 1, Style::default());
+                    }
+                    line.set_style(columns, theme.cursor.style());
+                }
+                LineRender {
+                    text: line.text.as_str().into(),
+                    styles: line.styles.as_slice().into(),
+                    ..Default::default()
+                }
+            };
+            lines.push((LineNumber::new(start + i + 1), render));
         }
+        let render = TextRender { 
+            lines: lines.into(),
+            name: key.name.as_str().into(),
+            modified: key.modified,
+            read_only: key.read_only,
+            focused: view.focused,
+            alignment: text.alignment,
+        };
+        view.render = Some((key, render.clone()));
+        render
     }
 }
 
@@ -326,7 +829,7 @@ impl AsyncWidget for ARW<Window> {
             set.spawn(async move { 
                 match child {
                     Left(window) => (i, Left(window.async_render().await)),
-                    Right(text) => (i, Right(text.async_render().await)),
+                    Right(view) => (i, Right(view.async_render().await)),
                 }
             });
         }
@@ -337,14 +840,16 @@ impl AsyncWidget for ARW<Window> {
         children.sort_by(|a, b| a.0.cmp(&b.0));
         WindowRender {
             split_dir: snapshot.split_dir,
+            sizes: snapshot.sizes(),
+            borders: snapshot.borders,
             children: children.into_iter().map(|c| c.1).collect()
         }
     }
 }
 
 #[async_trait]
-impl AsyncWidget for &'static RwLock<Root> {
-    async fn async_render(&self) -> WindowRender {
+impl AsyncWidget for ARW<Root> {
+    async fn async_render(&self) -> RootRender {
         let snapshot = self.read().await.clone();
         let len = snapshot.children.len();
         let mut set = JoinSet::new();
@@ -357,9 +862,13 @@ impl AsyncWidget for &'static RwLock<Root> {
             children.push(line);
         }
         children.sort_by(|a, b| a.0.cmp(&b.0));
-        WindowRender {
-            split_dir: snapshot.split_dir,
-            children: children.into_iter().map(|c| Left(c.1)).collect()
+        RootRender {
+            windows: WindowRender {
+                split_dir: snapshot.split_dir,
+                sizes: snapshot.sizes(),
+                borders: false,
+                children: children.into_iter().map(|c| Left(c.1)).collect()
+            },
         }
     }
 }
@@ -372,30 +881,70 @@ pub enum LayoutTypeRender {
     Content(TextRender),
 }
 
-#[derive(Default)]
-pub struct SpanRender {
-    pub characters: Vec<Char>,
-    pub alignment: Option<Alignment>,
-}
-
-#[derive(Default)]
+// renders share their contents so cached ones are cheap to hand out again
+#[derive(Default, Clone)]
 pub struct LineRender {
-    pub spans: Vec<SpanRender>,
+    pub text: Arc<str>,
+    pub styles: Arc<[StyleRun]>,
     pub alignment: Option<Alignment>,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct TextRender {
-    pub lines: Vec<(LineNumber, LineRender)>,
+    pub lines: Arc<[(LineNumber, LineRender)]>,
+    pub name: Arc<str>,
+    pub modified: bool,
+    pub read_only: bool,
+    pub focused: bool,
     pub alignment: Option<Alignment>,
 }
 
+impl TextRender {
+    // each pane says whether its own text has changed, whichever one is focused
+    pub fn title(&self) -> String {
+        let modified = if self.modified { " [+]" } else { "" };
+        let read_only = if self.read_only { " [ro]" } else { "" };
+        format!("{}{}{}", self.name, modified, read_only)
+    }
+}
+
+#[derive(Clone)]
+pub struct RootRender {
+    windows: WindowRender,
+}
+
+impl RootRender {
+    pub fn same_as(&self, other: &RootRender) -> bool {
+        self.windows.same_as(&other.windows)
+    }
+}
+
+#[derive(Clone)]
 pub struct WindowRender {
     split_dir: SplitDirection,
+    sizes: Vec<Constraint>,
+    borders: bool,
     children: Vec<Either<WindowRender, TextRender>>,
 }
 
-#[derive(Default, PartialEq, Eq,)] 
+impl WindowRender {
+    // true when both were built from the same cached text renders
+    pub fn same_as(&self, other: &WindowRender) -> bool {
+        self.split_dir == other.split_dir
+            && self.sizes == other.sizes
+            && self.borders == other.borders
+            && self.children.len() == other.children.len()
+            && self.children.iter().zip(other.children.iter()).all(|pair| match pair {
+                (Left(a), Left(b)) => a.same_as(b),
+                (Right(a), Right(b)) => Arc::ptr_eq(&a.lines, &b.lines)
+                    && Arc::ptr_eq(&a.name, &b.name)
+                    && a.focused == b.focused,
+                _ => false,
+            })
+    }
+}
+
+#[derive(Default, Clone, PartialEq, Eq,)] 
 pub struct LineNumber {
     num: usize,
     str: String,
@@ -422,113 +971,214 @@ impl Ord for LineNumber {
     }
 }
 
-impl WidgetRef for LineNumber {
-    fn render_ref(&self,area:Rect,buf: &mut Buffer) {
+impl LineNumber {
+    // the style comes from the text being drawn, so the theme is looked up once for all of them
+    fn render(&self, area: Rect, buf: &mut Buffer, style: Style) {
         Clear.render_ref(area, buf);
-        for (i, ch) in self.str.chars().enumerate() {
+        buf.set_style(area, style);
+        for (i, ch) in self.str.chars().take(area.width as usize).enumerate() {
             buf.get_mut(area.x + i as u16, area.y).set_symbol(&ch.to_string());
         }
     }
 }
 
-impl WidgetRef for Char {
-    fn render_ref(&self,area:Rect,buf: &mut Buffer) {
-        buf.set_style(area, self.style);
-        buf.get_mut(area.x, area.y).set_symbol(&self.char.to_string());
-    }
-}
-
-impl WidgetRef for SpanRender {
-    fn render_ref(&self,area:Rect,buf: &mut Buffer) {
-        // height is already 1
-        let mut i: u16 = 0;
-        for ch in self.characters.iter() {
-            let char_area = Rect {
-                x: area.x + i,
-                width: 1,
-                ..area
-            };
-            if !area.contains(Position::new(char_area.x, char_area.y)) {
-                break
-            }
-            ch.render_ref(char_area, buf);
-            i += 1;
-        }
-    }
-}
-
 impl WidgetRef for LineRender {
     fn render_ref(&self,area:Rect,buf: &mut Buffer) {
-        let mut offset: u16 = 0;
-        for span in self.spans.iter() {
-            let width = span.characters.len() as u16;
-            let area = Rect {
-                x: area.x + offset,
-                y: area.y,
-                width,
-                height: 1,
-            };
-            span.render_ref(area, buf);
-            offset += width;
+        let styles = self.styles
+            .iter()
+            .flat_map(|run| std::iter::repeat_n(run.style, run.len));
+        for (x, (ch, style)) in (area.x..area.right()).zip(self.text.chars().zip(styles)) {
+            // newlines and other control characters would move the terminal's cursor
+            buf.get_mut(x, area.y)
+                .set_char(if ch.is_control() { ' ' } else { ch })
+                .set_style(style);
         }
     }
 }
 
 impl WidgetRef for TextRender {
     fn render_ref(&self, area: Rect, buf: &mut Buffer) {
-        for (i, (ln_num, line)) in self.lines.iter().enumerate() {
+        // the last row is the title
+        let rows = area.height.saturating_sub(1) as usize;
+        let gutter = config().gutter.min(area.width);
+        let theme = theme();
+        let number_style = theme.line_number.style();
+        for (i, (ln_num, line)) in self.lines.iter().take(rows).enumerate() {
             let line_area = Rect {
                 height: 1,
                 y: area.y + i as u16,
                 ..area
             };
             let num_area = Rect {
-                width: 4,
+                width: gutter,
                 ..line_area
             };
             let content_area = Rect {
-                x: area.x + 4,
+                x: area.x + gutter,
+                width: area.width - gutter,
                 ..line_area
-            }.intersection(area);
+            };
 
-            ln_num.render_ref(num_area, buf);
+            ln_num.render(num_area, buf, number_style);
             line.render_ref(content_area, buf);
         }
+
+        // the last row is left free by the line filter in async_render
+        if area.height == 0 { return }
+        let title_area = Rect {
+            y: area.y + area.height - 1,
+            height: 1,
+            ..area
+        };
+        let style = if self.focused { theme.title_focused } else { theme.title };
+        buf.set_style(title_area, style.style());
+        buf.set_stringn(title_area.x + 1, title_area.y, self.title(), title_area.width.saturating_sub(1) as usize, Style::default());
     }
 }
 
 impl WidgetRef for WindowRender {
     fn render_ref(&self,area:Rect,buf: &mut Buffer) {
-        let windows: u16 = self.children.len().try_into().unwrap();
-        if windows == 0 { return; }
-        match self.split_dir {
-            SplitDirection::Horizontal => {
-                // split is horizontal. nested containers are stacked vertically
-                let offset = area.height / windows;
-                for (i, child) in self.children.iter().enumerate() {
-                    let area = Rect::new(
-                        area.x,
-                        if i == 0 { area.y } else { area.y + offset + 1 },
-                        area.width,
-                        offset
-                    );
-                    for_both!(child, c => c.render_ref(area, buf))
-                }
-            },
-            SplitDirection::Vertical => {
-                // split is vertical. nested containers are stacked horizontally
-                let offset = area.width / windows;
-                for (i, child) in self.children.iter().enumerate() {
-                    let area = Rect::new(
-                        if i == 0 { area.x } else { area.x + offset + 1 },
-                        area.y,
-                        offset,
-                        area.height,
-                    );
-                    for_both!(child, c => c.render_ref(area, buf))
-                }
-            },
+        if self.children.is_empty() { return; }
+        let areas = split_area(self.split_dir, &self.sizes, area);
+        let theme = theme();
+        for (child, child_area) in self.children.iter().zip(areas.iter()) {
+            match child {
+                Left(window) => window.render_ref(*child_area, buf),
+                Right(text) if self.borders => {
+                    let block = Block::bordered()
+                        .title(format!(" {} ", text.title()))
+                        .border_style(if text.focused { theme.border_focused.style() } else { theme.border.style() });
+                    block.render_ref(*child_area, buf);
+                    text.render_ref(block.inner(*child_area), buf);
+                },
+                Right(text) => text.render_ref(*child_area, buf),
+            }
         }
+
+        // separators fill the gaps the layout leaves between panes, borders do it themselves
+        if self.borders { return }
+        for pane in areas.iter().skip(1) {
+            match self.split_dir {
+                SplitDirection::Horizontal => {
+                    if pane.y == area.y { continue }
+                    for x in pane.left()..pane.right() {
+                        buf.get_mut(x, pane.y - 1).set_char('─');
+                    }
+                },
+                SplitDirection::Vertical => {
+                    if pane.x == area.x { continue }
+                    for y in pane.top()..pane.bottom() {
+                        buf.get_mut(pane.x - 1, y).set_char('│');
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl WidgetRef for RootRender {
+    fn render_ref(&self, area: Rect, buf: &mut Buffer) {
+        self.windows.render_ref(area, buf);
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn undo_insert() {
+        let mut text = Text::raw("abc\n".to_string());
+        text.insert(0, 1, "x").await;
+        assert_eq!(text.contents(), "axbc\n");
+        assert_eq!(text.undo().await, Some((0, 1)));
+        assert_eq!(text.contents(), "abc\n");
+        assert!(!text.is_modified());
+        assert_eq!(text.undo().await, None);
+        assert_eq!(text.redo().await, Some((0, 1)));
+        assert_eq!(text.contents(), "axbc\n");
+        assert_eq!(text.redo().await, None);
+    }
+
+    #[tokio::test]
+    async fn undo_paste() {
+        let mut text = Text::raw("abc\ndef\n".to_string());
+        let last = text.line(1);
+        text.insert(0, 1, "one\ntwo\nthree").await;
+        assert_eq!(text.contents(), "aone\ntwo\nthreebc\ndef\n");
+        assert_eq!(text.line_count(), 4);
+        assert_eq!(text.undo().await, Some((0, 1)));
+        assert_eq!(text.contents(), "abc\ndef\n");
+        assert_eq!(text.undo().await, None);
+        // the loaded line after the paste is back where it was
+        assert!(Arc::ptr_eq(&text.line(1), &last));
+        assert_eq!(last.read().await.text, "def\n");
+    }
+
+    #[tokio::test]
+    async fn edit_after_undoing_past_save() {
+        let mut text = Text::raw("abc\n".to_string());
+        text.insert(0, 0, "x").await;
+        text.insert(0, 0, "y").await;
+        text.saved_changes = text.changes;
+        text.undo().await;
+        assert!(text.is_modified());
+        // the saved state can't be reached by undoing or redoing any more
+        text.insert(0, 0, "z").await;
+        assert!(text.is_modified());
+        assert_eq!(text.contents(), "zxabc\n");
+        text.undo().await;
+        text.undo().await;
+        assert!(text.is_modified());
+        assert_eq!(text.contents(), "abc\n");
+    }
+
+    #[tokio::test]
+    async fn undo_after_redo_to_save() {
+        let mut text = Text::raw("abc\n".to_string());
+        text.insert(0, 0, "x").await;
+        text.saved_changes = text.changes;
+        text.undo().await;
+        text.redo().await;
+        assert!(!text.is_modified());
+        text.insert(0, 0, "y").await;
+        text.undo().await;
+        assert!(!text.is_modified());
+    }
+
+    #[tokio::test]
+    async fn failed_save_keeps_the_file() {
+        let path = std::env::temp_dir().join(format!("bespoke-ed-save-{}.txt", std::process::id()));
+        fs::write(&path, b"caf\xe9\n").unwrap();
+        let mut text = Text::open(&path).unwrap();
+        assert_eq!(text.encoding, Encoding::Latin1);
+        // latin-1 has no euro sign
+        text.insert(0, 0, "€").await;
+        assert!(text.save().is_err());
+        assert!(text.is_modified());
+        assert_eq!(fs::read(&path).unwrap(), b"caf\xe9\n");
+        let temp = path.with_file_name(format!(".{}.bespoke-ed~", path.file_name().unwrap().to_string_lossy()));
+        assert!(!temp.exists());
+
+        text.undo().await;
+        text.insert(0, 0, "é").await;
+        text.save().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"\xe9caf\xe9\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn undo_remove_all_lines() {
+        let mut text = Text::raw("abc\ndef\n".to_string());
+        text.line(0);
+        text.remove_lines(0..2);
+        assert_eq!(text.contents(), "\n");
+        assert_eq!(text.line_count(), 1);
+        assert_eq!(text.undo().await, Some((0, 0)));
+        assert_eq!(text.contents(), "abc\ndef\n");
+        assert_eq!(text.line_count(), 2);
+        assert!(!text.is_modified());
+        text.redo().await;
+        assert_eq!(text.contents(), "\n");
+    }
+}
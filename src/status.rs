@@ -0,0 +1,121 @@
+use anyhow::{bail, Result};
+use ratatui::{buffer::Buffer, layout::Rect, style::Style, widgets::WidgetRef};
+
+use crate::{config::config, primatives::{Text, View}, theme::theme, ARW};
+
+pub const DEFAULT_SEGMENTS: &str = "mode,file,modified,readonly,=,encoding,position,depth";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Segment {
+    Mode,
+    File,
+    Modified,
+    ReadOnly,
+    Encoding,
+    Position,
+    Depth,
+    Align, // everything after this is pushed to the right edge
+}
+
+impl Segment {
+    fn from_name(name: &str) -> Option<Segment> {
+        match name {
+            "mode" => Some(Segment::Mode),
+            "file" => Some(Segment::File),
+            "modified" => Some(Segment::Modified),
+            "readonly" => Some(Segment::ReadOnly),
+            "encoding" => Some(Segment::Encoding),
+            "position" => Some(Segment::Position),
+            "depth" => Some(Segment::Depth),
+            "=" => Some(Segment::Align),
+            _ => None,
+        }
+    }
+}
+
+// a comma separated list of segment names, like the default
+pub fn parse(segments: &str) -> Result<Vec<Segment>> {
+    segments
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| match Segment::from_name(name) {
+            Some(segment) => Ok(segment),
+            None => bail!("unknown status line segment: {}", name),
+        })
+        .collect()
+}
+
+// published by the control thread after every command
+#[derive(Clone)]
+pub struct Status {
+    pub segments: Vec<Segment>,
+    pub mode: &'static str,
+    pub depth: &'static str,
+    pub position: Option<(usize, usize)>, // zero based row and column
+    pub text: Option<ARW<Text>>,
+    pub view: Option<ARW<View>>, // the one with the focus
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self {
+            segments: parse(&config().statusline).unwrap_or_default(),
+            mode: "NORMAL",
+            depth: "Root",
+            position: None,
+            text: None,
+            view: None,
+        }
+    }
+}
+
+impl Status {
+    pub async fn render(&self) -> StatusRender {
+        let text = match self.text {
+            Some(ref text) => Some(text.read().await),
+            None => None,
+        };
+        let mut render = StatusRender::default();
+        let mut right = false;
+        for segment in self.segments.iter() {
+            let part = match (segment, text.as_ref()) {
+                (Segment::Align, _) => { right = true; continue },
+                (Segment::Mode, _) => self.mode.to_string(),
+                (Segment::Depth, _) => self.depth.to_string(),
+                (Segment::Position, _) => match self.position {
+                    Some((row, column)) => format!("{}:{}", row + 1, column + 1),
+                    None => continue,
+                },
+                (Segment::File, Some(text)) => text.name(),
+                (Segment::Modified, Some(text)) if text.is_modified() => "[+]".to_string(),
+                (Segment::ReadOnly, Some(text)) if text.read_only => "[ro]".to_string(),
+                (Segment::Encoding, Some(text)) => text.encoding.name().to_string(),
+                _ => continue,
+            };
+            let side = if right { &mut render.right } else { &mut render.left };
+            if !side.is_empty() { side.push(' ') }
+            side.push_str(&part);
+        }
+        render
+    }
+}
+
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct StatusRender {
+    left: String,
+    right: String,
+}
+
+impl WidgetRef for StatusRender {
+    fn render_ref(&self, area: Rect, buf: &mut Buffer) {
+        if area.height == 0 { return }
+        buf.set_style(area, theme().statusline.style());
+        let width = area.width as usize;
+        buf.set_stringn(area.x + 1, area.y, &self.left, width.saturating_sub(1), Style::default());
+
+        let right_len = self.right.chars().count() + 1;
+        if right_len + self.left.chars().count() + 2 > width { return }
+        let x = area.right() - right_len as u16;
+        buf.set_stringn(x, area.y, &self.right, right_len, Style::default());
+    }
+}
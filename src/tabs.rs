@@ -0,0 +1,212 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use ratatui::{buffer::Buffer, layout::Rect, style::Style, widgets::WidgetRef};
+use tokio::sync::RwLock;
+
+use crate::{
+    buffers::Buffers,
+    cmdline::CommandLine,
+    messages::{Level, Message, Messages},
+    primatives::{descendant_views, AsyncWidget, Root, RootRender},
+    status::{Status, StatusRender},
+    theme::{self, theme},
+    ARW
+};
+
+//
+// tab pages, each with its own window tree. the buffers, command line,
+// messages and status line are shared by all of them
+//
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TabIndex {
+    Absolute(usize), // zero based
+    Relative(isize),
+}
+
+pub struct Tabs {
+    pub area: Rect,
+    pub roots: Vec<ARW<Root>>,
+    pub current: usize,
+    pub buffers: Buffers,
+    pub messages: Messages,
+    pub cmdline: CommandLine,
+    pub status: Status,
+    pub pending: String, // keys typed towards a longer binding
+}
+
+impl Tabs {
+    pub fn new(area: Rect, root: ARW<Root>) -> Self {
+        Self {
+            area,
+            roots: vec![root],
+            current: 0,
+            buffers: Buffers::default(),
+            messages: Messages::default(),
+            cmdline: CommandLine::default(),
+            status: Status::default(),
+            pending: String::new(),
+        }
+    }
+
+    pub fn root(&self) -> ARW<Root> {
+        self.roots[self.current].clone()
+    }
+
+    pub fn post<T: Into<String>>(&mut self, level: Level, text: T) {
+        self.messages.post(level, text, self.area.width);
+    }
+
+    // the tab bar takes the top row once there's more than one tab, and the
+    // bottom two are kept for the status line, and messages or the command line
+    pub fn root_area(&self) -> Rect {
+        let bar = if self.roots.len() > 1 { 1 } else { 0 };
+        Rect {
+            y: self.area.y + bar.min(self.area.height),
+            height: self.area.height.saturating_sub(2 + bar),
+            ..self.area
+        }
+    }
+
+    pub async fn layout(&self) {
+        let area = self.root_area();
+        for root in self.roots.iter() {
+            root.write().await.area = area;
+            root.read().await.layout().await;
+        }
+    }
+
+    // relative moves wrap around the ends when `wrap` is set and stop at them otherwise
+    pub fn resolve(&self, index: TabIndex, wrap: bool) -> usize {
+        let last = self.roots.len() - 1;
+        match index {
+            TabIndex::Absolute(index) => index.min(last),
+            TabIndex::Relative(by) if wrap => {
+                (self.current as isize + by).rem_euclid(self.roots.len() as isize) as usize
+            },
+            TabIndex::Relative(by) => self.current.saturating_add_signed(by).min(last),
+        }
+    }
+}
+
+// the focused text's name, or the first one's when none of them has the focus
+async fn label(root: &ARW<Root>) -> String {
+    let views = descendant_views(root.read().await.children.clone()).await;
+    let mut name = String::new();
+    for view in views.iter() {
+        let view = view.read().await;
+        if name.is_empty() || view.focused { name = view.text.read().await.name() }
+        if view.focused { break }
+    }
+    match Path::new(&name).file_name() {
+        Some(file) => file.to_string_lossy().into_owned(),
+        None => name,
+    }
+}
+
+#[async_trait]
+impl AsyncWidget for &'static RwLock<Tabs> {
+    async fn async_render(&self) -> TabsRender {
+        let tabs = self.read().await;
+        let mut bar = Vec::new();
+        if tabs.roots.len() > 1 {
+            for (i, root) in tabs.roots.iter().enumerate() {
+                bar.push(format!(" {} {} ", i + 1, label(root).await));
+            }
+        }
+        TabsRender {
+            bar,
+            current: tabs.current,
+            root: tabs.root().async_render().await,
+            message: tabs.messages.current().cloned(),
+            prompt: tabs.cmdline.active.then_some((tabs.cmdline.input.clone(), tabs.cmdline.cursor)),
+            status: tabs.status.render().await,
+            theme: theme::generation(),
+            pending: tabs.pending.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TabsRender {
+    bar: Vec<String>,
+    current: usize,
+    root: RootRender,
+    message: Option<Message>,
+    prompt: Option<(String, usize)>, // input and cursor
+    status: StatusRender,
+    theme: u64, // the generation it was drawn for
+    pending: String,
+}
+
+impl TabsRender {
+    pub fn same_as(&self, other: &TabsRender) -> bool {
+        self.bar == other.bar
+            && self.current == other.current
+            && self.message == other.message
+            && self.prompt == other.prompt
+            && self.status == other.status
+            && self.pending == other.pending
+            && self.theme == other.theme
+            && self.root.same_as(&other.root)
+    }
+}
+
+impl WidgetRef for TabsRender {
+    fn render_ref(&self, area: Rect, buf: &mut Buffer) {
+        let bar = if self.bar.is_empty() { 0 } else { 1 };
+        let root_area = Rect {
+            y: area.y + bar.min(area.height),
+            height: area.height.saturating_sub(2 + bar),
+            ..area
+        };
+        self.root.render_ref(root_area, buf);
+
+        if area.height < 2 + bar { return }
+        if bar == 1 {
+            let bar_area = Rect { height: 1, ..area };
+            buf.set_style(bar_area, theme().tab_bar.style());
+            let mut x = area.x;
+            for (i, label) in self.bar.iter().enumerate() {
+                let start = x;
+                let width = area.right().saturating_sub(x) as usize;
+                x = buf.set_stringn(x, area.y, label, width, Style::default()).0;
+                // the current tab doesn't pick up anything from the bar's style
+                if i == self.current {
+                    let current = Rect { x: start, width: x - start, ..bar_area };
+                    buf.set_style(current, Style::reset());
+                    buf.set_style(current, theme().tab_current.style());
+                }
+            }
+        }
+
+        let status_area = Rect {
+            y: area.bottom() - 2,
+            height: 1,
+            ..area
+        };
+        self.status.render_ref(status_area, buf);
+
+        let y = area.bottom() - 1;
+        if let Some((ref input, cursor)) = self.prompt {
+            // keep the cursor on screen when the input is wider than the prompt
+            let skip = (cursor + 2).saturating_sub(area.width as usize);
+            let line: String = format!(":{}", input).chars().skip(skip).collect();
+            buf.set_stringn(area.x, y, line, area.width as usize, Style::default());
+            let x = area.x + (cursor + 1 - skip) as u16;
+            if x < area.right() {
+                let cell = buf.get_mut(x, y);
+                cell.set_style(theme().cursor.style());
+            }
+        } else if let Some(ref message) = self.message {
+            buf.set_stringn(area.x, y, &message.text, area.width as usize, message.level.style());
+        }
+        // pending keys go in the bottom right corner, over anything long under them
+        let width = self.pending.chars().count() as u16;
+        if width > 0 && self.prompt.is_none() && width < area.width {
+            let x = area.right() - width - 1;
+            buf.set_stringn(x, y, format!("{} ", self.pending), width as usize + 1, Style::default());
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use std::{collections::BTreeMap, env, fs, sync::{atomic::{AtomicU64, Ordering}, Arc, RwLock}};
+
+use anyhow::{bail, Context, Result};
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use crate::config::{self, config};
+
+//
+// named sets of styles for everything drawn. a theme file only has to give
+// the styles it changes from the default. colours are brought down to what
+// the terminal can show once, when the theme is put in place
+//
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("default", ""),
+    ("gruvbox", r##"
+        cursor = { fg = "#282828", bg = "#ebdbb2" }
+        selection = { fg = "#ebdbb2", bg = "#504945" }
+        search = { fg = "#282828", bg = "#fabd2f" }
+        line_number = { fg = "#7c6f64" }
+        title = { fg = "#a89984", bg = "#3c3836" }
+        title_focused = { fg = "#282828", bg = "#83a598" }
+        border = { fg = "#504945" }
+        border_focused = { fg = "#83a598" }
+        statusline = { fg = "#ebdbb2", bg = "#3c3836" }
+        tab_bar = { fg = "#a89984", bg = "#3c3836" }
+        tab_current = { fg = "#ebdbb2", bg = "#282828", bold = true }
+        warning = { fg = "#fabd2f" }
+        error = { fg = "#fb4934" }
+
+        [syntax]
+        comment = { fg = "#928374", italic = true }
+        string = { fg = "#b8bb26" }
+        keyword = { fg = "#fb4934" }
+        function = { fg = "#fabd2f" }
+        type = { fg = "#83a598" }
+        number = { fg = "#d3869b" }
+    "##),
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeStyle {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underlined: bool,
+    pub reversed: bool,
+}
+
+impl ThemeStyle {
+    const fn new(fg: Option<Color>, bg: Option<Color>) -> ThemeStyle {
+        ThemeStyle { fg, bg, bold: false, dim: false, italic: false, underlined: false, reversed: false }
+    }
+
+    const fn reversed(mut self) -> ThemeStyle {
+        self.reversed = true;
+        self
+    }
+
+    pub fn style(&self) -> Style {
+        let mut style = Style::default();
+        if let Some(fg) = self.fg { style = style.fg(fg) }
+        if let Some(bg) = self.bg { style = style.bg(bg) }
+        for (on, modifier) in [
+            (self.bold, Modifier::BOLD),
+            (self.dim, Modifier::DIM),
+            (self.italic, Modifier::ITALIC),
+            (self.underlined, Modifier::UNDERLINED),
+            (self.reversed, Modifier::REVERSED),
+        ] {
+            if on { style = style.add_modifier(modifier) }
+        }
+        style
+    }
+
+    fn downsample(&mut self, depth: Depth) {
+        self.fg = self.fg.map(|color| downsample(color, depth));
+        self.bg = self.bg.map(|color| downsample(color, depth));
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    #[serde(skip)]
+    pub name: String,
+    pub cursor: ThemeStyle, // the zipper's focus, and the command line's cursor
+    pub selection: ThemeStyle,
+    pub search: ThemeStyle,
+    pub line_number: ThemeStyle,
+    pub title: ThemeStyle, // the row under each text
+    pub title_focused: ThemeStyle,
+    pub border: ThemeStyle,
+    pub border_focused: ThemeStyle,
+    pub statusline: ThemeStyle,
+    pub tab_bar: ThemeStyle,
+    pub tab_current: ThemeStyle,
+    pub info: ThemeStyle,
+    pub warning: ThemeStyle,
+    pub error: ThemeStyle,
+    pub syntax: BTreeMap<String, ThemeStyle>, // by scope, like `comment` or `string.escape`
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let syntax = [
+            ("comment", ThemeStyle::new(Some(Color::DarkGray), None)),
+            ("string", ThemeStyle::new(Some(Color::Green), None)),
+            ("keyword", ThemeStyle::new(Some(Color::Magenta), None)),
+            ("function", ThemeStyle::new(Some(Color::Blue), None)),
+            ("type", ThemeStyle::new(Some(Color::Yellow), None)),
+            ("number", ThemeStyle::new(Some(Color::Cyan), None)),
+        ];
+        Self {
+            name: "default".to_string(),
+            cursor: ThemeStyle::new(Some(Color::Black), Some(Color::White)),
+            selection: ThemeStyle::new(Some(Color::White), Some(Color::Blue)),
+            search: ThemeStyle::new(Some(Color::Black), Some(Color::Yellow)),
+            line_number: ThemeStyle::default(),
+            title: ThemeStyle::default().reversed(),
+            title_focused: ThemeStyle::new(Some(Color::Cyan), None).reversed(),
+            border: ThemeStyle::default(),
+            border_focused: ThemeStyle::new(Some(Color::Cyan), None),
+            statusline: ThemeStyle::default().reversed(),
+            tab_bar: ThemeStyle::default().reversed(),
+            tab_current: ThemeStyle { bold: true, ..ThemeStyle::default() },
+            info: ThemeStyle::default(),
+            warning: ThemeStyle::new(Some(Color::Yellow), None),
+            error: ThemeStyle::new(Some(Color::Red), None),
+            syntax: syntax.into_iter().map(|(scope, style)| (scope.to_string(), style)).collect(),
+        }
+    }
+}
+
+impl Theme {
+    fn downsample(&mut self, depth: Depth) {
+        for style in [
+            &mut self.cursor, &mut self.selection, &mut self.search, &mut self.line_number,
+            &mut self.title, &mut self.title_focused, &mut self.border, &mut self.border_focused,
+            &mut self.statusline, &mut self.tab_bar, &mut self.tab_current,
+            &mut self.info, &mut self.warning, &mut self.error,
+        ] {
+            style.downsample(depth);
+        }
+        self.syntax.values_mut().for_each(|style| style.downsample(depth));
+    }
+}
+
+static THEME: RwLock<Option<Arc<Theme>>> = RwLock::new(None);
+// bumped whenever the theme changes, so cached renders are drawn again
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// the theme everything is drawn with, the default until `set` has run
+pub fn theme() -> Arc<Theme> {
+    if let Some(theme) = THEME.read().unwrap().as_ref() { return theme.clone() }
+    Arc::new(Theme::default())
+}
+
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+pub fn set(mut theme: Theme) {
+    theme.downsample(depth());
+    *THEME.write().unwrap() = Some(Arc::new(theme));
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+// a file in the themes directory, or one of the built in themes
+pub fn load(name: &str) -> Result<Theme> {
+    let file = config::dir().map(|dir| dir.join("themes").join(format!("{}.toml", name)));
+    let content = match (file, BUILTIN.iter().find(|(builtin, _)| *builtin == name)) {
+        (Some(file), _) if file.is_file() => {
+            fs::read_to_string(&file).with_context(|| format!("couldn't read {}", file.display()))?
+        },
+        (_, Some((_, content))) => content.to_string(),
+        _ => bail!("no theme named {}", name),
+    };
+    let mut theme: Theme = toml::from_str(&content).with_context(|| format!("theme {} has mistakes in it", name))?;
+    theme.name = name.to_string();
+    // scopes the theme leaves out keep their default styles
+    let mut syntax = Theme::default().syntax;
+    syntax.append(&mut theme.syntax);
+    theme.syntax = syntax;
+    Ok(theme)
+}
+
+// the built in themes and the ones in the themes directory
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN.iter().map(|(name, _)| name.to_string()).collect();
+    let files = config::dir().and_then(|dir| fs::read_dir(dir.join("themes")).ok());
+    for entry in files.into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            names.extend(path.file_stem().map(|stem| stem.to_string_lossy().into_owned()));
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Depth {
+    Ansi, // the 16 named colours
+    Indexed, // the 256 colour palette
+    TrueColor,
+}
+
+pub const DEPTHS: &[&str] = &["auto", "16", "256", "truecolor"];
+
+// the `colors` setting, or a guess from the environment when it's auto
+pub fn depth() -> Depth {
+    match config().colors.as_str() {
+        "16" => Depth::Ansi,
+        "256" => Depth::Indexed,
+        "truecolor" => Depth::TrueColor,
+        _ => {
+            let colorterm = env::var("COLORTERM").unwrap_or_default();
+            let term = env::var("TERM").unwrap_or_default();
+            if colorterm == "truecolor" || colorterm == "24bit" {
+                Depth::TrueColor
+            } else if term.contains("256color") {
+                Depth::Indexed
+            } else {
+                Depth::Ansi
+            }
+        },
+    }
+}
+
+// xterm's values for the 16 named colours, in palette order
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn rgb_of_index(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI[index as usize].1,
+        16..=231 => {
+            let i = index - 16;
+            (CUBE[(i / 36) as usize], CUBE[(i / 6 % 6) as usize], CUBE[(i % 6) as usize])
+        },
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        },
+    }
+}
+
+fn nearest_ansi(rgb: (u8, u8, u8)) -> Color {
+    ANSI.iter().min_by_key(|(_, ansi)| distance(rgb, *ansi)).unwrap().0
+}
+
+// the closer of the colour cube and the grey ramp
+fn nearest_index(rgb: (u8, u8, u8)) -> u8 {
+    let level = |c: u8| CUBE.iter().enumerate().min_by_key(|(_, l)| (c as i32 - **l as i32).abs()).unwrap().0 as u8;
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let average = (rgb.0 as u16 + rgb.1 as u16 + rgb.2 as u16) / 3;
+    let grey = 232 + ((average.saturating_sub(3)) / 10).min(23) as u8;
+    if distance(rgb, rgb_of_index(grey)) < distance(rgb, rgb_of_index(cube)) { grey } else { cube }
+}
+
+fn downsample(color: Color, depth: Depth) -> Color {
+    match (color, depth) {
+        (Color::Rgb(r, g, b), Depth::Indexed) => Color::Indexed(nearest_index((r, g, b))),
+        (Color::Rgb(r, g, b), Depth::Ansi) => nearest_ansi((r, g, b)),
+        (Color::Indexed(index), Depth::Ansi) => nearest_ansi(rgb_of_index(index)),
+        (color, _) => color,
+    }
+}
@@ -1,25 +1,54 @@
 use crossterm::{
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     terminal::{
-        disable_raw_mode, enable_raw_mode, EnterAlternateScreen,
-        LeaveAlternateScreen,
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement,
+        EnterAlternateScreen, LeaveAlternateScreen,
     }, ExecutableCommand
 };
 use ratatui::{
     backend::Backend, prelude::{CrosstermBackend, Terminal},
 };
-use std::{io::stdout, panic};
+use std::{io::stdout, panic, sync::atomic::{AtomicBool, Ordering}};
 
 use anyhow::{Ok, Result};
 
+use crate::config::config;
+
+// set when the terminal took the flags, so they're only popped if they were pushed
+static ENHANCED: AtomicBool = AtomicBool::new(false);
+
+// whether keys come with the enhancement, so Tab and Ctrl+i are different keys
+pub fn enhanced() -> bool {
+    ENHANCED.load(Ordering::Relaxed)
+}
+
 pub fn init_app() -> Result<Terminal<impl Backend>> {
-    stdout().execute(EnterAlternateScreen)?;
+    // raw mode goes first, it's what fails when there's no terminal to read keys from
     enable_raw_mode()?;
+    stdout().execute(EnterAlternateScreen)?;
+    // lets keys the terminal would otherwise send the same, like Tab and Ctrl+i,
+    // be told apart, and reports Super and Meta along with the other modifiers
+    if supports_keyboard_enhancement().unwrap_or(false) {
+        stdout().execute(PushKeyboardEnhancementFlags(
+            KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS
+        ))?;
+        ENHANCED.store(true, Ordering::Relaxed);
+    }
+    if config().mouse { stdout().execute(EnableMouseCapture)?; }
+    // so a paste arrives whole rather than as the keys that would type it
+    stdout().execute(EnableBracketedPaste)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
     terminal.clear()?;
     Ok(terminal)
 }
 
 pub fn teardown_app() -> Result<()> {
+    if ENHANCED.load(Ordering::Relaxed) { stdout().execute(PopKeyboardEnhancementFlags)?; }
+    if config().mouse { stdout().execute(DisableMouseCapture)?; }
+    stdout().execute(DisableBracketedPaste)?;
     stdout().execute(LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
@@ -28,6 +57,9 @@ pub fn teardown_app() -> Result<()> {
 pub fn install_panic_hook() {
     let original_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
+        if ENHANCED.load(Ordering::Relaxed) { stdout().execute(PopKeyboardEnhancementFlags).unwrap(); }
+        if config().mouse { stdout().execute(DisableMouseCapture).unwrap(); }
+        stdout().execute(DisableBracketedPaste).unwrap();
         stdout().execute(LeaveAlternateScreen).unwrap();
         disable_raw_mode().unwrap();
         original_hook(panic_info);
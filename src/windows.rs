@@ -0,0 +1,313 @@
+use std::{cmp::min, sync::Arc};
+
+use anyhow::{bail, Result};
+use either::*;
+use ratatui::layout::{Constraint, Rect};
+use tokio::sync::RwLock;
+
+use crate::{
+    buffers::BufferId,
+    primatives::{descendant_texts, split_area, Root, SplitDirection, View, Window},
+    zipper::{self, DynZipper},
+    ARW
+};
+
+//
+// window commands. they work on paths through the tree, the same child
+// indices the zippers walk, and rebuild the zipper from the root afterwards
+//
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Resize {
+    By(i16),
+    To(Constraint),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+type Child = Either<ARW<Window>, ARW<View>>;
+
+async fn window_at(root: &ARW<Root>, path: &[usize]) -> Option<ARW<Window>> {
+    let (first, rest) = path.split_first()?;
+    let mut window = root.read().await.children.get(*first)?.clone();
+    for &index in rest {
+        let child = window.read().await.children.get(index)?.clone();
+        window = child.left()?;
+    }
+    Some(window)
+}
+
+// the split direction and children of the root, or the window at `path`
+async fn children_at(root: &ARW<Root>, path: &[usize]) -> Option<(SplitDirection, Vec<Child>)> {
+    if path.is_empty() {
+        let root = root.read().await;
+        return Some((root.split_dir, root.children.iter().cloned().map(Left).collect()))
+    }
+    let window = window_at(root, path).await?;
+    let window = window.read().await;
+    Some((window.split_dir, window.children.clone()))
+}
+
+// follows the first or last child of each window down to a text
+async fn descend(root: &ARW<Root>, mut path: Vec<usize>, last: bool) -> Vec<usize> {
+    while let Some((_, children)) = children_at(root, &path).await {
+        if children.is_empty() { break }
+        let index = if last { children.len() - 1 } else { 0 };
+        path.push(index);
+        if children[index].is_right() { break }
+    }
+    path
+}
+
+// focuses the line the view at `path` was last on
+pub async fn enter(root: &ARW<Root>, path: &[usize]) -> DynZipper {
+    let zipper = zipper::from_path(root, path).await;
+    match zipper.text() {
+        Some(text) => {
+            let (row, _) = text.view().read().await.cursor;
+            text.line_at(row).await
+        },
+        None => zipper,
+    }
+}
+
+// the path to the first view on screen that `matches`, and the view
+pub async fn find_view(root: &ARW<Root>, matches: impl Fn(&View) -> bool) -> Option<(Vec<usize>, ARW<View>)> {
+    let mut stack: Vec<(Vec<usize>, Child)> = root.read().await.children.iter()
+        .enumerate()
+        .rev()
+        .map(|(i, window)| (vec![i], Left(window.clone())))
+        .collect();
+    while let Some((path, child)) = stack.pop() {
+        match child {
+            Left(window) => {
+                for (i, child) in window.read().await.children.iter().enumerate().rev() {
+                    let mut path = path.clone();
+                    path.push(i);
+                    stack.push((path, child.clone()));
+                }
+            },
+            Right(view) => if matches(&*view.read().await) { return Some((path, view)) },
+        }
+    }
+    None
+}
+
+// puts `opened` next to the focused text and moves the focus into it
+pub async fn split(
+    root: &ARW<Root>,
+    zipper: &DynZipper,
+    split_dir: SplitDirection,
+    opened: View,
+) -> Result<DynZipper> {
+    let Some(text) = zipper::first_text(zipper).await else { bail!("no window to split") };
+    let window = text.window();
+    let mut window = window.write().await;
+    let index = text.index();
+    let mut path = text.path();
+    path.pop();
+    // same direction splits become siblings, otherwise the text is wrapped in a new window
+    if window.children.len() == 1 || window.split_dir == split_dir {
+        window.split_dir = split_dir;
+        window.add_view(opened, index + 1);
+        path.push(index + 1);
+    } else {
+        let mut inner = Window::new(split_dir, window.area);
+        inner.borders = window.borders;
+        inner.children.push(window.children[index].clone());
+        inner.add_view(opened, 1);
+        window.children[index] = Left(Arc::new(RwLock::new(inner)));
+        path.extend([index, 1]);
+    }
+    drop(window);
+    zipper.highlight(false).await;
+    Ok(enter(root, &path).await)
+}
+
+// removes the focused text's window, and any windows that leaves empty
+pub async fn close(root: &ARW<Root>, zipper: &DynZipper, force: bool) -> Result<DynZipper> {
+    let Some(text) = zipper::first_text(zipper).await else { bail!("no window to close") };
+    let texts = descendant_texts(root.read().await.children.clone()).await;
+    if texts.len() == 1 { bail!("can't close the last window") }
+    let shown = texts.iter().filter(|t| Arc::ptr_eq(t, &text.focus())).count();
+    if !force && shown == 1 && text.focus().read().await.is_modified() {
+        bail!("buffer has unsaved changes, add ! to close it anyway")
+    }
+    zipper.highlight(false).await;
+
+    let mut path = text.path();
+    let mut removed = path.pop().unwrap_or(0);
+    text.window().write().await.remove_child(removed);
+    while let Some((_, children)) = children_at(root, &path).await {
+        if !children.is_empty() || path.is_empty() { break }
+        removed = path.pop().unwrap_or(0);
+        match window_at(root, &path).await {
+            Some(parent) => { parent.write().await.remove_child(removed); },
+            None => { root.write().await.remove_window(removed); },
+        }
+    }
+
+    // the focus goes to whatever took the closed window's place
+    let len = children_at(root, &path).await.map(|(_, c)| c.len()).unwrap_or(0);
+    path.push(min(removed, len.saturating_sub(1)));
+    let path = descend(root, path, false).await;
+    Ok(enter(root, &path).await)
+}
+
+// closes every window but the focused one
+pub async fn only(root: &ARW<Root>, zipper: &DynZipper, force: bool) -> Result<DynZipper> {
+    let Some(text) = zipper::first_text(zipper).await else { bail!("no window to keep") };
+    let focus = text.focus();
+    let view = text.view();
+    if !force {
+        for other in descendant_texts(root.read().await.children.clone()).await {
+            if !Arc::ptr_eq(&other, &focus) && other.read().await.is_modified() {
+                bail!("other buffers have unsaved changes, add ! to close them anyway")
+            }
+        }
+    }
+    zipper.highlight(false).await;
+
+    let borders = text.window().read().await.borders;
+    let mut root_lock = root.write().await;
+    let mut window = Window::new(root_lock.split_dir, root_lock.area);
+    window.borders = borders;
+    window.insert_view(view, 0);
+    root_lock.children = vec![Arc::new(RwLock::new(window))];
+    root_lock.sizes.clear();
+    drop(root_lock);
+    Ok(enter(root, &[0, 0]).await)
+}
+
+// moves to the neighbouring window on screen, going up the tree until a split
+// in the right direction has a sibling on that side
+pub async fn focus(root: &ARW<Root>, zipper: &DynZipper, direction: Direction) -> Option<DynZipper> {
+    let text = zipper::first_text(zipper).await?;
+    let path = text.path();
+    let (axis, step) = match direction {
+        Direction::Left => (SplitDirection::Vertical, -1),
+        Direction::Right => (SplitDirection::Vertical, 1),
+        Direction::Up => (SplitDirection::Horizontal, -1),
+        Direction::Down => (SplitDirection::Horizontal, 1),
+    };
+    for depth in (0..path.len()).rev() {
+        let (split_dir, children) = children_at(root, &path[..depth]).await?;
+        if split_dir != axis { continue }
+        let Some(index) = path[depth].checked_add_signed(step).filter(|i| *i < children.len()) else { continue };
+
+        let mut target = path[..depth].to_vec();
+        target.push(index);
+        // coming from the right or below, the nearest text is the last one
+        let target = descend(root, target, step < 0).await;
+        zipper.highlight(false).await;
+        return Some(enter(root, &target).await)
+    }
+    None
+}
+
+// resizes the focused pane within the nearest split that has more than one pane
+pub async fn resize(root: &ARW<Root>, zipper: &DynZipper, resize: Resize) -> Result<()> {
+    let Some(text) = zipper::first_text(zipper).await else { bail!("no window to resize") };
+    let path = text.path();
+    for depth in (0..path.len()).rev() {
+        let Some((split_dir, children)) = children_at(root, &path[..depth]).await else { continue };
+        if children.len() < 2 { continue }
+
+        let index = path[depth];
+        let update = |sizes: &mut Vec<Constraint>, area: Rect| {
+            sizes.resize(sizes.len().max(children.len()), Constraint::Fill(1));
+            sizes[index] = match resize {
+                Resize::To(size) => size,
+                // a cell at a time from however big the pane is now, whatever its size was given as
+                Resize::By(by) => {
+                    let pane = split_area(split_dir, &sizes[..children.len()], area)[index];
+                    let current = match split_dir {
+                        SplitDirection::Vertical => pane.width,
+                        SplitDirection::Horizontal => pane.height,
+                    };
+                    Constraint::Length(current.saturating_add_signed(by).max(1))
+                },
+            };
+        };
+        match window_at(root, &path[..depth]).await {
+            Some(window) => {
+                let mut window = window.write().await;
+                let area = window.area;
+                update(&mut window.sizes, area)
+            },
+            None => {
+                let mut root = root.write().await;
+                let area = root.area;
+                update(&mut root.sizes, area)
+            },
+        }
+        return Ok(())
+    }
+    bail!("there's only one window")
+}
+
+// sets the size of one pane in the split at `path`, for when it's dragged
+pub async fn set_size(root: &ARW<Root>, path: &[usize], index: usize, size: Constraint) {
+    let Some((_, children)) = children_at(root, path).await else { return };
+    if index >= children.len() { return }
+    let update = |sizes: &mut Vec<Constraint>| {
+        sizes.resize(sizes.len().max(children.len()), Constraint::Fill(1));
+        sizes[index] = size;
+    };
+    match window_at(root, path).await {
+        Some(window) => update(&mut window.write().await.sizes),
+        None => update(&mut root.write().await.sizes),
+    }
+}
+
+// gives every pane in every split an equal share again
+pub async fn equalize(root: &ARW<Root>) {
+    let mut stack = {
+        let mut root = root.write().await;
+        root.sizes.clear();
+        root.children.clone()
+    };
+    while let Some(window) = stack.pop() {
+        let mut window = window.write().await;
+        window.sizes.clear();
+        stack.extend(window.children.iter().filter_map(|c| c.clone().left()));
+    }
+}
+
+// puts a view of another buffer in place of the focused one
+pub async fn show(root: &ARW<Root>, zipper: &DynZipper, mut view: View) -> Result<DynZipper> {
+    let Some(text) = zipper::first_text(zipper).await else { bail!("no window to show it in") };
+    view.height = text.view().read().await.height;
+    zipper.highlight(false).await;
+    text.window().write().await.children[text.index()] = Right(Arc::new(RwLock::new(view)));
+    Ok(enter(root, &text.path()).await)
+}
+
+// swaps every view of `buffer` for whatever `replace` gives back
+pub async fn replace_views(root: &ARW<Root>, buffer: BufferId, replace: impl Fn() -> View) {
+    let mut stack = root.read().await.children.clone();
+    while let Some(window) = stack.pop() {
+        let mut window = window.write().await;
+        for child in window.children.iter_mut() {
+            match child {
+                Left(inner) => stack.push(inner.clone()),
+                Right(view) => {
+                    let height = {
+                        let view = view.read().await;
+                        if view.buffer != buffer { continue }
+                        view.height
+                    };
+                    let mut replacement = replace();
+                    replacement.height = height;
+                    *view = Arc::new(RwLock::new(replacement));
+                },
+            }
+        }
+    }
+}
@@ -1,33 +1,42 @@
 
 //time 2 rewrite
-use std::cmp::min;
+use std::{cmp::min, ops::Range};
 
 use async_trait::async_trait;
 use either::*;
 use ratatui::layout::Rect;
-use tokio::sync::RwLock;
 
-use crate::{primatives::{AsyncWidget, Char, Line, Root, Span, Text, Window}, ARW};
+use crate::{primatives::{descendant_texts, split_area, AsyncWidget, Char, Line, Root, Span, Text, View, Window}, ARW};
 
 pub type DynZipper = Box<dyn Zipper + Send + Sync>;
 
 #[async_trait]
 pub trait Zipper {
-    // async fn insert(&mut self, char: char) -> DynZipper;
-    // async fn delete(&mut self);
     async fn highlight(&self, hl: bool) { let _ = hl; }
 
+    // the text, row and column the focus starts at, if it's inside a text
+    fn cursor(&self) -> Option<(TextZipper, usize, usize)> { None }
+
+    // the text the focus is in, if it's at or below one
+    fn text(&self) -> Option<TextZipper> { None }
+
+    // which level of the tree the focus is on
+    fn depth(&self) -> &'static str;
+
     async fn parent(&self) -> DynZipper;
     async fn child(&self, index: usize) -> DynZipper;
 
     async fn move_left(&self) -> DynZipper;
     async fn move_right(&self) -> DynZipper;
+
+    // every text at or beneath the focus
+    async fn texts(&self) -> Vec<ARW<Text>>;
 }
 
 #[derive(Clone)]
 pub struct RootZipper {
     area: Rect,
-    focus: &'static RwLock<Root>,
+    focus: ARW<Root>,
     children: Vec<ARW<Window>>
 }
 
@@ -36,70 +45,77 @@ pub struct WindowZipper {
     area: Rect,
     focus: ARW<Window>,
     parent: Box<Either<RootZipper, WindowZipper>>,
-    left: Vec<Either<ARW<Window>, ARW<Text>>>,
-    right: Vec<Either<ARW<Window>, ARW<Text>>>,
-    children: Vec<Either<ARW<Window>, ARW<Text>>>,
+    left: Vec<Either<ARW<Window>, ARW<View>>>,
+    right: Vec<Either<ARW<Window>, ARW<View>>>,
+    children: Vec<Either<ARW<Window>, ARW<View>>>,
 }
 
 #[derive(Clone)]
 pub struct TextZipper {
     area: Rect,
     focus: ARW<Text>,
+    view: ARW<View>, // the window's view the text is seen through
     parent: WindowZipper,
-    left: Vec<Either<ARW<Window>, ARW<Text>>>,
-    right: Vec<Either<ARW<Window>, ARW<Text>>>,
-    children: Vec<ARW<Line>>
+    left: Vec<Either<ARW<Window>, ARW<View>>>,
+    right: Vec<Either<ARW<Window>, ARW<View>>>,
+    lines: usize, // lines are loaded on the way down, so only the count is kept
 }
 
 #[derive(Clone)]
 pub struct LineZipper {
     row: usize,
+    lines: usize,
     focus: ARW<Line>,
     parent: TextZipper,
-    left: Vec<ARW<Line>>,
-    right: Vec<ARW<Line>>,
-    children: Vec<ARW<Span>>
+    children: Vec<Span>
 }
 
 #[derive(Clone)]
 pub struct SpanZipper {
     column: usize, // column of the first character
-    focus: ARW<Span>,
+    focus: Span,
     parent: LineZipper,
-    left: Vec<ARW<Span>>,
-    right: Vec<ARW<Span>>,
-    children: Vec<ARW<Char>>
+    left: Vec<Span>,
+    right: Vec<Span>,
+    children: Vec<Char>
 }
 
 #[derive(Clone)]
 pub struct CharZipper {
     column: usize,
-    focus: ARW<Char>,
+    focus: Option<Char>, // none just past the end of a line without a newline
     parent: SpanZipper,
-    left: Vec<ARW<Char>>,
-    right: Vec<ARW<Char>>,
+    left: Vec<Char>,
+    right: Vec<Char>,
 }
 
 
 impl RootZipper {
-    pub async fn new(root: &'static RwLock<Root>) -> Self {
+    pub async fn new(root: &ARW<Root>) -> Self {
+        let (area, children) = {
+            let root = root.read().await;
+            (root.area, root.children.clone())
+        };
         Self {
-            focus: root,
-            area: root.read().await.area.clone(),
-            children: root.read().await.children.clone(),
+            focus: root.clone(),
+            area,
+            children,
         }
     }
 }
 
 impl WindowZipper {
     pub async fn new(index: usize, parent: Either<RootZipper, WindowZipper>) -> Self {
-        let (siblings, area): (Vec<_>, Rect) = match parent {
-            Left(ref rz) => (rz.children.iter().cloned().map(|c| Left(c)).collect(), rz.area.clone()),
-            Right(ref wz) => (wz.children.clone(), wz.area.clone()),
+        let siblings: Vec<_> = match parent {
+            Left(ref rz) => rz.children.iter().cloned().map(|c| Left(c)).collect(),
+            Right(ref wz) => wz.children.clone(),
         };
         let index = min(index, siblings.len());
         let focus = siblings[index].clone().left().unwrap();
-        let children = focus.read().await.children.clone();
+        let (children, area) = {
+            let window = focus.read().await;
+            (window.children.clone(), window.area)
+        };
 
         Self {
             area,
@@ -114,15 +130,23 @@ impl WindowZipper {
 
 impl TextZipper {
     pub async fn new(index: usize, parent: WindowZipper) -> Self {
-        let area = parent.area.clone();
         let siblings = parent.children.clone();
         let index = min(index, siblings.len());
-        let focus = siblings[index].clone().right().unwrap();
-        let children = focus.read().await.lines.clone();
+        let view = siblings[index].clone().right().unwrap();
+        let focus = view.read().await.text.clone();
+        let area = {
+            let window = parent.focus.read().await;
+            split_area(window.split_dir, &window.sizes(), window.area)
+                .get(index)
+                .copied()
+                .unwrap_or(window.area)
+        };
+        let lines = focus.read().await.line_count();
 
         Self {
             focus,
-            children,
+            view,
+            lines,
             parent,
             area,
             left: siblings[0..index].iter().cloned().collect(),
@@ -131,29 +155,177 @@ impl TextZipper {
     }
 }
 
-impl LineZipper {
-    pub async fn new(index: usize, parent: TextZipper) -> Self {
-        let siblings = parent.children.clone();
-        let row = min(index, siblings.len());
-        let focus = siblings[row].clone();
-        let children = focus.read().await.spans.clone();
+impl WindowZipper {
+    // child indices from the root down to this window
+    pub fn path(&self) -> Vec<usize> {
+        let mut path = match *self.parent {
+            Left(_) => Vec::new(),
+            Right(ref wz) => wz.path(),
+        };
+        path.push(self.left.len());
+        path
+    }
+}
+
+impl TextZipper {
+    pub fn path(&self) -> Vec<usize> {
+        let mut path = self.parent.path();
+        path.push(self.left.len());
+        path
+    }
+
+    pub fn index(&self) -> usize {
+        self.left.len()
+    }
+
+    pub fn focus(&self) -> ARW<Text> {
+        self.focus.clone()
+    }
+
+    pub fn view(&self) -> ARW<View> {
+        self.view.clone()
+    }
+
+    pub fn window(&self) -> ARW<Window> {
+        self.parent.focus.clone()
+    }
 
-        let scroll_offset = parent.focus.read().await.scroll_offset;
-        let cursor_pos_relative = index.saturating_sub(scroll_offset);
+    // rebuilds the path down to a whole line
+    pub async fn line_at(&self, row: usize) -> DynZipper {
+        let text = TextZipper::new(self.left.len(), self.parent.clone()).await;
+        let line = LineZipper::new(row, text).await;
+        line.highlight(true).await;
+        Box::new(line)
+    }
 
-        if cursor_pos_relative < scroll_offset {
-            parent.focus.write().await.scroll_offset -= 1;
-        } else if cursor_pos_relative >= scroll_offset + parent.area.height as usize {
-            parent.focus.write().await.scroll_offset += 1;
+    // rebuilds the path down to the character at (row, column), or
+    // the line when it's empty, picking up any edits made to the text
+    pub async fn cursor_at(&self, row: usize, column: usize) -> DynZipper {
+        let text = TextZipper::new(self.left.len(), self.parent.clone()).await;
+        let line = LineZipper::new(row, text).await;
+        if line.children.is_empty() {
+            line.highlight(true).await;
+            return Box::new(line)
+        }
+        let index = line.children
+            .iter()
+            .position(|span| span.range().contains(&column))
+            .unwrap_or(line.children.len() - 1);
+        let span = SpanZipper::new(index, line).await;
+        let char = CharZipper::new(column.saturating_sub(span.column), span).await;
+        char.highlight(true).await;
+        Box::new(char)
+    }
+
+    // highlights `columns` of `row` in this view only, and remembers where the focus is
+    async fn show(&self, hl: bool, row: usize, column: usize, columns: Range<usize>) {
+        let mut view = self.view.write().await;
+        if hl {
+            view.cursor = (row, column);
+            view.highlight = Some((row, columns));
+        } else {
+            view.highlight = None;
         }
+    }
+}
+
+// walks down from the root, for when the tree under an existing zipper has changed
+pub async fn from_path(root: &ARW<Root>, path: &[usize]) -> DynZipper {
+    let mut zipper: DynZipper = Box::new(RootZipper::new(root).await);
+    for &index in path {
+        zipper = zipper.child(index).await;
+    }
+    zipper
+}
+
+// rebuilds a line or character focus, scrolling it back into view after its text changed size
+pub async fn refocus(zipper: &DynZipper) -> Option<DynZipper> {
+    let (text, row, column) = zipper.cursor()?;
+    zipper.highlight(false).await;
+    Some(match zipper.depth() {
+        "Line" => text.line_at(row).await,
+        _ => text.cursor_at(row, column).await,
+    })
+}
+
+// the focused text, or the first one beneath the focus
+pub async fn first_text(zipper: &DynZipper) -> Option<TextZipper> {
+    if let Some(text) = zipper.text() { return Some(text) }
+    if zipper.texts().await.is_empty() { return None }
+    let mut zipper = zipper.child(0).await;
+    // each step goes a level further down, until a text or a branch without any
+    loop {
+        if let Some(text) = zipper.text() { return Some(text) }
+        if zipper.texts().await.is_empty() { return None }
+        zipper = zipper.child(0).await;
+    }
+}
+
+// inserts in front of the focus. the focus stays on the same character
+pub async fn insert(zipper: &DynZipper, content: &str) -> Option<DynZipper> {
+    let (text, row, column) = zipper.cursor()?;
+    if text.focus.read().await.read_only { return None }
+    zipper.highlight(false).await;
+    text.focus.write().await.insert(row, column, content).await;
+
+    let newlines = content.matches('\n').count();
+    let column = match content.rfind('\n') {
+        Some(i) => content[i + 1..].chars().count(),
+        None => column + content.chars().count(),
+    };
+    Some(text.cursor_at(row + newlines, column).await)
+}
+
+// moves the focus `rows` lines and `columns` characters within its text, staying
+// on whole lines when it's on one and only moving up or down
+pub async fn step(zipper: &DynZipper, rows: isize, columns: isize) -> Option<DynZipper> {
+    let (text, row, column) = zipper.cursor()?;
+    let (row, column) = {
+        let focus = text.focus.read().await;
+        let row = row.saturating_add_signed(rows).min(focus.line_count() - 1);
+        // up to the end of the line, where typing adds to it
+        (row, column.saturating_add_signed(columns).min(focus.line_len(row)))
+    };
+    zipper.highlight(false).await;
+    Some(match zipper.depth() {
+        "Line" if columns == 0 => text.line_at(row).await,
+        _ => text.cursor_at(row, column).await,
+    })
+}
+
+// removes the character in front of the focus, joining lines at the start of one
+pub async fn backspace(zipper: &DynZipper) -> Option<DynZipper> {
+    let (text, row, column) = zipper.cursor()?;
+    if row == 0 && column == 0 { return None }
+    if text.focus.read().await.read_only { return None }
+    zipper.highlight(false).await;
+
+    let mut focus = text.focus.write().await;
+    let (row, column) = if column > 0 {
+        (row, column - 1)
+    } else {
+        (row - 1, focus.line_len(row - 1))
+    };
+    focus.remove(row, column).await;
+    drop(focus);
+
+    Some(text.cursor_at(row, column).await)
+}
+
+impl LineZipper {
+    pub async fn new(index: usize, parent: TextZipper) -> Self {
+        let lines = parent.lines;
+        let row = min(index, lines - 1);
+        parent.view.write().await.scroll_to(row);
+        let focus = parent.focus.write().await.line(row);
+        let children = focus.read().await.spans();
 
         Self {
             row,
+            lines,
             focus,
             children,
             parent,
-            left: siblings[0..row].iter().cloned().collect(),
-            right: siblings[row + 1..].iter().cloned().collect(),
         }
     }
 }
@@ -161,22 +333,16 @@ impl LineZipper {
 impl SpanZipper {
     pub async fn new(index: usize, parent: LineZipper) -> Self {
         let siblings = parent.children.clone();
-
-        let mut column = 0_usize;
-        for (i, sib) in siblings.iter().enumerate() {
-            if i < index {
-                column += sib.read().await.characters.len();
-            } else {
-                break
-            }
-        }
-
         let index = min(index, siblings.len() - 1);
-        let focus = siblings[index].clone();
-        let children = focus.read().await.characters.clone();
+        let focus = siblings[index];
+        let children = parent.focus.read().await
+            .chars()
+            .skip(focus.start)
+            .take(focus.len)
+            .collect();
 
         Self {
-            column,
+            column: focus.start,
             focus,
             children,
             parent,
@@ -189,18 +355,25 @@ impl SpanZipper {
 impl CharZipper {
     pub async fn new(index: usize, parent: SpanZipper) -> Self {
         let siblings = parent.children.clone();
-        let index = min(index, siblings.len());
-        let focus = siblings[index].clone();
+        let index = min(index, Self::last(&parent));
+        let focus = siblings.get(index).copied();
 
         Self {
             column: parent.column + index,
             focus,
+            left: siblings[0..index].to_vec(),
+            right: siblings[min(index + 1, siblings.len())..].to_vec(),
             parent,
-            left: siblings[0..index].iter().cloned().collect(),
-            right: siblings[index + 1..].iter().cloned().collect(),
         }
     }
 
+    // the last line of a text may not end in a newline, so the place after its
+    // last character is a position of its own, where typing appends to the line
+    fn last(span: &SpanZipper) -> usize {
+        let open = span.right.is_empty() && span.children.last().is_some_and(|c| c.char != '\n');
+        if open { span.children.len() } else { span.children.len() - 1 }
+    }
+
     pub async fn move_left_or_cousin(&self) -> DynZipper {
         if self.left.len() >  0 {
             self.move_left().await
@@ -227,6 +400,7 @@ impl CharZipper {
 
 #[async_trait]
 impl Zipper for RootZipper {
+    fn depth(&self) -> &'static str { "Root" }
     async fn parent(&self) -> DynZipper {
         Box::new(self.clone())
     }
@@ -243,10 +417,15 @@ impl Zipper for RootZipper {
     async fn move_right(&self) -> DynZipper {
         Box::new(self.clone())
     }
+
+    async fn texts(&self) -> Vec<ARW<Text>> {
+        descendant_texts(self.children.clone()).await
+    }
 }
 
 #[async_trait]
 impl Zipper for WindowZipper {
+    fn depth(&self) -> &'static str { "Window" }
     // TODO: highlight/no highlight
     async fn parent(&self) -> DynZipper {
         self.focus.no_highlight().await;
@@ -280,20 +459,29 @@ impl Zipper for WindowZipper {
 
         for_both!(parent, p => p.child(index + 1).await)
     }
+
+    async fn texts(&self) -> Vec<ARW<Text>> {
+        descendant_texts(vec![self.focus.clone()]).await
+    }
 }
 
 #[async_trait]
 impl Zipper for TextZipper {
+    fn depth(&self) -> &'static str { "Text" }
+    fn text(&self) -> Option<TextZipper> {
+        Some(self.clone())
+    }
+    async fn texts(&self) -> Vec<ARW<Text>> {
+        vec![self.focus.clone()]
+    }
     async fn parent(&self) -> DynZipper {
         self.highlight(false).await;
         self.parent.highlight(true).await;
         Box::new(self.parent.clone())
     }
     async fn child(&self, index: usize) -> DynZipper {
-        if self.children.len() == 0 { return Box::new(self.clone()) }
+        if self.lines == 0 { return Box::new(self.clone()) }
         self.highlight(false).await;
-        let the_kids = self.children.clone();
-        let index = min(index, the_kids.len());
         let child = LineZipper::new(index, self.clone()).await;
         child.highlight(true).await;
         Box::new(child)
@@ -321,6 +509,16 @@ impl Zipper for TextZipper {
 
 #[async_trait]
 impl Zipper for LineZipper {
+    fn depth(&self) -> &'static str { "Line" }
+    fn cursor(&self) -> Option<(TextZipper, usize, usize)> {
+        Some((self.parent.clone(), self.row, 0))
+    }
+    fn text(&self) -> Option<TextZipper> {
+        Some(self.parent.clone())
+    }
+    async fn texts(&self) -> Vec<ARW<Text>> {
+        self.parent.texts().await
+    }
     async fn parent(&self) -> DynZipper {
         self.highlight(false).await;
         self.parent.highlight(true).await;
@@ -337,35 +535,39 @@ impl Zipper for LineZipper {
     }
 
     async fn move_left(&self) -> DynZipper {
-        let index = self.left.len();
-        if index == 0 { return Box::new(self.clone()) }
+        if self.row == 0 { return Box::new(self.clone()) }
         self.highlight(false).await;
 
-        let child = self.parent.child(index - 1).await;
+        let child = self.parent.child(self.row - 1).await;
         child.highlight(true).await;
         child
     }
     async fn move_right(&self) -> DynZipper {
-        let index = self.left.len();
-        if self.right.len() == 0 { return Box::new(self.clone()) }
+        if self.row + 1 >= self.lines { return Box::new(self.clone()) }
         self.highlight(false).await;
 
-        let child = self.parent.child(index + 1).await;
+        let child = self.parent.child(self.row + 1).await;
         child.highlight(true).await;
         child
     }
 
     async fn highlight(&self, hl: bool) {
-        if hl {
-            self.focus.highlight().await;
-        } else {
-            self.focus.no_highlight().await;
-        }
+        self.parent.show(hl, self.row, 0, 0..usize::MAX).await;
     }
 }
 
 #[async_trait]
 impl Zipper for SpanZipper {
+    fn depth(&self) -> &'static str { "Span" }
+    fn cursor(&self) -> Option<(TextZipper, usize, usize)> {
+        Some((self.parent.parent.clone(), self.parent.row, self.column))
+    }
+    fn text(&self) -> Option<TextZipper> {
+        Some(self.parent.parent.clone())
+    }
+    async fn texts(&self) -> Vec<ARW<Text>> {
+        self.parent.texts().await
+    }
     async fn parent(&self) -> DynZipper {
         self.highlight(false).await;
         self.parent.highlight(true).await;
@@ -401,15 +603,21 @@ impl Zipper for SpanZipper {
     }
 
     async fn highlight(&self, hl: bool) {
-        if hl {
-            self.focus.highlight().await;
-        } else {
-            self.focus.no_highlight().await;
-        }
+        self.parent.parent.show(hl, self.parent.row, self.column, self.focus.range()).await;
     }
 }
 #[async_trait]
 impl Zipper for CharZipper {
+    fn depth(&self) -> &'static str { "Char" }
+    fn cursor(&self) -> Option<(TextZipper, usize, usize)> {
+        Some((self.parent.parent.parent.clone(), self.parent.parent.row, self.column))
+    }
+    fn text(&self) -> Option<TextZipper> {
+        Some(self.parent.parent.parent.clone())
+    }
+    async fn texts(&self) -> Vec<ARW<Text>> {
+        self.parent.texts().await
+    }
     async fn parent(&self) -> DynZipper {
         self.highlight(false).await;
         self.parent.highlight(true).await;
@@ -431,7 +639,7 @@ impl Zipper for CharZipper {
     }
     async fn move_right(&self) -> DynZipper {
         let index = self.left.len();
-        if self.right.len() == 0 { return Box::new(self.clone()) }
+        if index >= Self::last(&self.parent) { return Box::new(self.clone()) }
         self.highlight(false).await;
 
         let sib = self.parent.child(index + 1).await;
@@ -440,14 +648,49 @@ impl Zipper for CharZipper {
     }
 
     async fn highlight(&self, hl: bool) {
-        if hl {
-            self.focus.highlight().await;
-        } else {
-            self.focus.no_highlight().await;
-        }
+        let line = &self.parent.parent;
+        line.parent.show(hl, line.row, self.column, self.column..self.column + 1).await;
     }
 }
 
 
 
 
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    use crate::primatives::SplitDirection;
+    use super::*;
+
+    async fn open(contents: &str) -> (ARW<Text>, DynZipper) {
+        let text = Arc::new(RwLock::new(Text::raw(contents.to_string())));
+        let mut root = Root::new(SplitDirection::Horizontal, Rect::new(0, 0, 40, 10));
+        root.add_window(SplitDirection::Horizontal, 0);
+        root.children[0].write().await.add_view(View::new(0, text.clone()), 0);
+        let root = Arc::new(RwLock::new(root));
+        root.read().await.layout().await;
+        let zipper: DynZipper = Box::new(RootZipper::new(&root).await);
+        let zipper = first_text(&zipper).await.unwrap().cursor_at(0, 0).await;
+        (text, zipper)
+    }
+
+    #[tokio::test]
+    async fn append_to_last_line() {
+        let (text, mut zipper) = open("abc").await;
+        for _ in 0..5 { zipper = step(&zipper, 0, 1).await.unwrap() }
+        assert_eq!(zipper.cursor().unwrap().2, 3);
+        zipper = insert(&zipper, "x").await.unwrap();
+        zipper = insert(&zipper, "y").await.unwrap();
+        assert_eq!(text.read().await.contents(), "abcxy");
+        assert_eq!(zipper.cursor().unwrap().2, 5);
+
+        // a line ending in a newline stops on it instead
+        let (_, mut zipper) = open("abc\n").await;
+        for _ in 0..5 { zipper = step(&zipper, 0, 1).await.unwrap() }
+        assert_eq!(zipper.cursor().unwrap().2, 3);
+        zipper = zipper.move_right().await;
+        assert_eq!(zipper.cursor().unwrap().2, 3);
+    }
+}